@@ -1,15 +1,22 @@
 use cold::{link::link, opt::parse_opts};
 use tracing::info;
+use tracing_subscriber::filter::LevelFilter;
 
 fn main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt::init();
-
     let args = std::env::args().skip(1).collect::<Vec<_>>();
-    info!("Launched with args: {:?}", args);
 
-    // parse arguments
+    // parse arguments first, --quiet decides the log level
     let opt = parse_opts(&args)?;
 
+    if opt.quiet {
+        tracing_subscriber::fmt()
+            .with_max_level(LevelFilter::ERROR)
+            .init();
+    } else {
+        tracing_subscriber::fmt::init();
+    }
+
+    info!("Launched with args: {:?}", args);
     info!("Parsed options: {opt:?}");
 
     link(&opt)?;
@@ -1,2 +1,4 @@
 pub mod link;
 pub mod opt;
+pub mod pattern;
+pub mod script;
@@ -1,9 +1,20 @@
-use crate::opt::{FileOpt, ObjectFileOpt, Opt};
+use crate::opt::{CompressDebugSections, FileOpt, ObjectFileOpt, Opt, TextRelPolicy};
+use crate::pattern::glob_match;
+use crate::script::{read_script, Expression, Script, SortOrder, DISCARD};
 use anyhow::{anyhow, bail, Context};
 use object::elf::{
-    ProgramHeader64, Sym64, DF_1_PIE, DT_FLAGS_1, DT_JMPREL, DT_NEEDED, DT_PLTGOT, DT_PLTREL,
-    DT_PLTRELSZ, DT_RELA, R_X86_64_JUMP_SLOT,
+    FileHeader64, ProgramHeader64, Sym64, DF_1_NODEFLIB, DF_1_NODELETE, DF_1_NOOPEN, DF_1_NOW,
+    DF_1_ORIGIN, DF_1_PIE, DF_BIND_NOW, DF_SYMBOLIC, DT_FLAGS, DT_FLAGS_1, DT_JMPREL, DT_NEEDED,
+    DT_PLTGOT, DT_PLTREL, DT_PLTRELSZ, DT_RELA, DT_RELACOUNT, DT_RELAENT, DT_RELASZ, DT_RPATH,
+    DT_RUNPATH, ET_DYN, EV_CURRENT, R_X86_64_32, R_X86_64_32S, R_X86_64_64, R_X86_64_DTPOFF32,
+    R_X86_64_DTPOFF64, R_X86_64_GLOB_DAT, R_X86_64_GOT32, R_X86_64_GOTOFF64, R_X86_64_GOTPC32,
+    R_X86_64_GOTPC32_TLSDESC, R_X86_64_GOTPC64, R_X86_64_GOTPCREL, R_X86_64_GOTPCRELX,
+    R_X86_64_GOTTPOFF, R_X86_64_IRELATIVE, R_X86_64_JUMP_SLOT, R_X86_64_PC32, R_X86_64_PC64,
+    R_X86_64_PLT32, R_X86_64_RELATIVE, R_X86_64_REX_GOTPCRELX, R_X86_64_SIZE32, R_X86_64_SIZE64,
+    R_X86_64_TLSDESC, R_X86_64_TLSDESC_CALL, R_X86_64_TLSGD, R_X86_64_TLSLD, R_X86_64_TPOFF32,
+    R_X86_64_TPOFF64, SHT_PROGBITS, STT_FUNC, STT_GNU_IFUNC, STV_HIDDEN,
 };
+use object::read::elf::{Dyn as _, FileHeader as _};
 use object::write::elf::*;
 use object::{
     elf::{DT_GNU_HASH, DT_HASH, DT_NULL, DT_SONAME, DT_STRSZ, DT_STRTAB, DT_SYMENT, DT_SYMTAB},
@@ -13,11 +24,30 @@ use object::{
     },
     Object, ObjectSection, ObjectSymbol,
 };
-use object::{LittleEndian, ObjectKind};
-use std::{collections::BTreeMap, os::unix::fs::PermissionsExt, path::PathBuf};
+use object::{Architecture, Endianness, LittleEndian, ObjectKind};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    os::unix::fs::PermissionsExt,
+    path::PathBuf,
+};
 use tracing::{info, info_span, warn};
 use typed_arena::Arena;
 
+mod assert;
+mod debug;
+mod demangle;
+mod eh_frame;
+mod ifunc;
+mod location;
+mod map;
+mod memory;
+mod needed;
+mod property;
+mod provide;
+mod segment;
+mod tls;
+mod undefined;
+
 fn lookup_file(name: &str, paths: &Vec<String>) -> anyhow::Result<PathBuf> {
     for path in paths {
         let mut p = PathBuf::from(path);
@@ -59,19 +89,474 @@ pub fn path_resolution(opt: &Opt) -> anyhow::Result<Opt> {
             continue;
         }
     }
+
+    if opt.copy_dt_needed_entries {
+        copy_needed_libraries(&mut opt)?;
+    }
+    check_needed_libraries(&opt)?;
     Ok(opt)
 }
 
+/// Parse DT_NEEDED entries of a shared library, returns None if not a shared
+/// library
+fn needed_libraries(path: &str) -> anyhow::Result<Option<Vec<String>>> {
+    let data = std::fs::read(path).context(format!("Reading file {}", path))?;
+    let data = data.as_slice();
+    let Ok(header) = FileHeader64::<LittleEndian>::parse(data) else {
+        // archive, linker script etc.
+        return Ok(None);
+    };
+    let endian = header.endian()?;
+    if header.e_type(endian) != ET_DYN {
+        return Ok(None);
+    }
+
+    let mut needed = vec![];
+    let sections = header.sections(endian, data)?;
+    if let Some((dynamic, string_section_index)) = sections.dynamic(endian, data)? {
+        let strings = sections.strings(endian, data, string_section_index)?;
+        for entry in dynamic {
+            if entry.d_tag(endian) == DT_NEEDED as u64 {
+                let name = entry.string(endian, strings)?;
+                needed.push(std::str::from_utf8(name)?.to_string());
+            }
+        }
+    }
+    Ok(Some(needed))
+}
+
+/// Find a DT_NEEDED entry in -rpath-link or -L directories, names with a
+/// slash are paths like for ld.so
+fn lookup_needed_library(name: &str, opt: &Opt) -> Option<PathBuf> {
+    if name.contains('/') {
+        let path = PathBuf::from(name);
+        return path.is_file().then_some(path);
+    }
+    let search_dir: Vec<String> = opt
+        .rpath_link
+        .iter()
+        .chain(opt.search_dir.iter())
+        .cloned()
+        .collect();
+    lookup_file(name, &search_dir).ok()
+}
+
+/// --copy-dt-needed-entries: link against libraries needed by shared
+/// libraries recursively, as if they were given on the command line
+fn copy_needed_libraries(opt: &mut Opt) -> anyhow::Result<()> {
+    let mut visited = BTreeSet::new();
+    let mut queue = vec![];
+    for obj_file in &opt.obj_file {
+        if let ObjectFileOpt::File(file_opt) = obj_file {
+            visited.insert(PathBuf::from(&file_opt.name));
+            queue.push(file_opt.name.clone());
+        }
+    }
+
+    while let Some(path) = queue.pop() {
+        let Some(needed) = needed_libraries(&path)? else {
+            continue;
+        };
+        for name in needed {
+            // missing ones are reported by check_needed_libraries
+            let Some(found) = lookup_needed_library(&name, opt) else {
+                continue;
+            };
+            if !visited.insert(found.clone()) {
+                continue;
+            }
+            info!("Copying DT_NEEDED {} of {}", found.display(), path);
+            let found = format!("{}", found.display());
+            opt.obj_file.push(ObjectFileOpt::File(FileOpt {
+                name: found.clone(),
+                as_needed: false,
+            }));
+            queue.push(found);
+        }
+    }
+    Ok(())
+}
+
+/// Check that transitive DT_NEEDED entries of shared libraries can be found
+/// in -rpath-link or -L directories
+fn check_needed_libraries(opt: &Opt) -> anyhow::Result<()> {
+    let mut visited = BTreeSet::new();
+    let mut queue = vec![];
+    for obj_file in &opt.obj_file {
+        if let ObjectFileOpt::File(file_opt) = obj_file {
+            let path = PathBuf::from(&file_opt.name);
+            if let Some(file_name) = path.file_name() {
+                visited.insert(file_name.to_string_lossy().to_string());
+            }
+            queue.push(file_opt.name.clone());
+        }
+    }
+
+    while let Some(path) = queue.pop() {
+        let Some(needed) = needed_libraries(&path)? else {
+            continue;
+        };
+        for name in needed {
+            if !visited.insert(name.clone()) {
+                continue;
+            }
+            match lookup_needed_library(&name, opt) {
+                Some(found) => queue.push(format!("{}", found.display())),
+                None => warn!(
+                    "{}, needed by {}, not found (try using -rpath-link)",
+                    name, path
+                ),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Architecture, ELF class and endianness of a file
+type Target = (Architecture, bool, Endianness);
+
+/// Emulation => BFD-style output format, target of input files
+const EMULATIONS: &[(&str, &str, Target)] = &[
+    (
+        "elf_x86_64",
+        "elf64-x86-64",
+        (Architecture::X86_64, true, Endianness::Little),
+    ),
+    (
+        "elf32_x86_64",
+        "elf32-x86-64",
+        (Architecture::X86_64_X32, false, Endianness::Little),
+    ),
+    (
+        "elf_i386",
+        "elf32-i386",
+        (Architecture::I386, false, Endianness::Little),
+    ),
+    (
+        "aarch64linux",
+        "elf64-littleaarch64",
+        (Architecture::Aarch64, true, Endianness::Little),
+    ),
+    (
+        "aarch64linuxb",
+        "elf64-bigaarch64",
+        (Architecture::Aarch64, true, Endianness::Big),
+    ),
+    (
+        "armelf_linux_eabi",
+        "elf32-littlearm",
+        (Architecture::Arm, false, Endianness::Little),
+    ),
+    (
+        "armelfb_linux_eabi",
+        "elf32-bigarm",
+        (Architecture::Arm, false, Endianness::Big),
+    ),
+    (
+        "elf64lriscv",
+        "elf64-littleriscv",
+        (Architecture::Riscv64, true, Endianness::Little),
+    ),
+    (
+        "elf64ppc",
+        "elf64-powerpc",
+        (Architecture::PowerPc64, true, Endianness::Big),
+    ),
+    (
+        "elf64lppc",
+        "elf64-powerpcle",
+        (Architecture::PowerPc64, true, Endianness::Little),
+    ),
+];
+
+/// The only target of output: ELF header and relocations are x86_64 only
+const OUTPUT_TARGET: Target = (Architecture::X86_64, true, Endianness::Little);
+
+/// BFD-style output format name and target of the emulation, which must
+/// produce the output target
+fn emulation_target(emulation: &str) -> anyhow::Result<(&'static str, Target)> {
+    match EMULATIONS.iter().find(|(name, _, _)| *name == emulation) {
+        None => bail!("Unknown emulation: {}", emulation),
+        Some((_, _, target)) if *target != OUTPUT_TARGET => bail!(
+            "Unsupported emulation {}: only elf_x86_64 is supported",
+            emulation
+        ),
+        Some((_, format, target)) => Ok((format, *target)),
+    }
+}
+
+/// BFD-style output format name of the emulation
+pub fn output_format(opt: &Opt) -> anyhow::Result<&'static str> {
+    let emulation = opt.emulation.as_deref().unwrap_or("elf_x86_64");
+    Ok(emulation_target(emulation)?.0)
+}
+
+/// Readable name of a target, like "X86_64 64-bit little endian"
+fn target_name((architecture, is_64, endianness): Target) -> String {
+    format!(
+        "{:?} {}-bit {} endian",
+        architecture,
+        if is_64 { 64 } else { 32 },
+        match endianness {
+            Endianness::Little => "little",
+            Endianness::Big => "big",
+        }
+    )
+}
+
+// section must not be garbage collected, not defined in object crate yet
+const SHF_GNU_RETAIN: u32 = 0x200000;
+
+/// Sections removed by --strip-debug: debug info, and links to external debug
+/// info which is meaningless without it
+fn is_debug_section(name: &str) -> bool {
+    const STRIP_DEBUG_SECTIONS: &[&str] = &[".gnu_debuglink", ".gnu_debugaltlink"];
+    name.starts_with(".debug")
+        || name.starts_with(".zdebug")
+        || STRIP_DEBUG_SECTIONS.contains(&name)
+}
+
+/// Sections that are always kept by --gc-sections, like KEEP() in the
+/// default linker script of GNU ld
+fn is_gc_root_section(name: &str) -> bool {
+    name == ".init"
+        || name == ".fini"
+        || [
+            ".init_array",
+            ".fini_array",
+            ".preinit_array",
+            ".ctors",
+            ".dtors",
+            ".eh_frame",
+            ".note",
+        ]
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+}
+
+/// Record global symbols defined and referenced by a file, dynamic symbols
+/// for shared libraries
+fn collect_symbols<'data>(
+    obj: &object::File<'data>,
+    defined: &mut BTreeSet<Vec<u8>>,
+    undefined: &mut BTreeSet<Vec<u8>>,
+) -> anyhow::Result<()> {
+    let symbols = if obj.kind() == ObjectKind::Dynamic {
+        obj.dynamic_symbols()
+    } else {
+        obj.symbols()
+    };
+    for symbol in symbols {
+        if symbol.is_local() {
+            continue;
+        }
+        let name = symbol.name_bytes()?.to_vec();
+        if symbol.is_undefined() {
+            undefined.insert(name);
+        } else {
+            defined.insert(name);
+        }
+    }
+    Ok(())
+}
+
+/// Extract members of an archive defining undefined symbols, until no more
+/// symbols are resolved by it, returns whether any member was extracted.
+/// Archives without symbol index have every member taken on the first scan
+fn extract_archive_members<'data>(
+    file: &'data ObjectFile,
+    extracted: &mut BTreeSet<u64>,
+    rescan: bool,
+    defined: &mut BTreeSet<Vec<u8>>,
+    undefined: &mut BTreeSet<Vec<u8>>,
+    objs: &mut Vec<(String, object::File<'data>)>,
+) -> anyhow::Result<bool> {
+    let ar = object::read::archive::ArchiveFile::parse(file.content.as_slice())
+        .context(format!("Parsing file {} as archive", file.name))?;
+    let Some(index) = ar.symbols()? else {
+        if rescan {
+            return Ok(false);
+        }
+        // no symbol index to look up, take every member
+        warn!("Archive {} has no symbol index", file.name);
+        for member in ar.members() {
+            let member = member?;
+            let name = format!("{}({})", file.name, std::str::from_utf8(member.name())?);
+            info!("Parsing {}", name);
+            let obj = object::File::parse(member.data(file.content.as_slice())?)
+                .context(format!("Parsing file {} as object", name))?;
+            collect_symbols(&obj, defined, undefined)?;
+            objs.push((name, obj));
+        }
+        return Ok(true);
+    };
+    let index = index
+        .map(|symbol| symbol.map(|symbol| (symbol.name(), symbol.offset())))
+        .collect::<object::Result<Vec<_>>>()?;
+
+    let mut any_extracted = false;
+    loop {
+        let mut resolved = false;
+        for (symbol_name, offset) in &index {
+            if !undefined.contains(*symbol_name)
+                || defined.contains(*symbol_name)
+                || extracted.contains(&offset.0)
+            {
+                continue;
+            }
+            extracted.insert(offset.0);
+            let member = ar.member(*offset)?;
+            let name = format!("{}({})", file.name, std::str::from_utf8(member.name())?);
+            info!(
+                "Extracting {} for {}",
+                name,
+                String::from_utf8_lossy(symbol_name)
+            );
+            let obj = object::File::parse(member.data(file.content.as_slice())?)
+                .context(format!("Parsing file {} as object", name))?;
+            collect_symbols(&obj, defined, undefined)?;
+            objs.push((name, obj));
+            resolved = true;
+            any_extracted = true;
+        }
+        if !resolved {
+            break;
+        }
+    }
+    Ok(any_extracted)
+}
+
+/// Mark sections reachable from the roots for --gc-sections, returns the
+/// (file index, section index) pairs of live sections
+fn gc_sections(
+    opt: &Opt,
+    script: &Script,
+    objs: &[(String, object::File)],
+) -> anyhow::Result<BTreeSet<(usize, usize)>> {
+    // global symbol name => defining (file index, section index)
+    let mut global_symbols = BTreeMap::new();
+    for (file_index, (_name, obj)) in objs.iter().enumerate() {
+        if obj.kind() == ObjectKind::Dynamic {
+            continue;
+        }
+        for symbol in obj.symbols() {
+            if symbol.is_global() && !symbol.is_undefined() {
+                if let Some(section_index) = symbol.section_index() {
+                    global_symbols.insert(symbol.name()?, (file_index, section_index.0));
+                }
+            }
+        }
+    }
+
+    // roots: entrypoint, exported symbols, and sections that must be kept
+    let mut worklist = vec![];
+    if let Some(entry) = global_symbols.get(opt.entry_symbol.as_deref().unwrap_or("_start")) {
+        worklist.push(*entry);
+    }
+    if opt.shared || opt.export_dynamic || opt.gc_keep_exported {
+        // global symbols go to .dynsym, external callers may reference them
+        worklist.extend(global_symbols.values().copied());
+    }
+    for (file_index, (name, obj)) in objs.iter().enumerate() {
+        if obj.kind() == ObjectKind::Dynamic {
+            continue;
+        }
+        for section in obj.sections() {
+            let retain = match section.flags() {
+                object::SectionFlags::Elf { sh_flags } => ((sh_flags as u32) & SHF_GNU_RETAIN) != 0,
+                _ => false,
+            };
+            let section_name = section.name()?;
+            if retain || is_gc_root_section(section_name) || script.keeps(name, section_name) {
+                worklist.push((file_index, section.index().0));
+            }
+        }
+    }
+
+    // follow relocations to mark sections as live
+    let mut live_sections = BTreeSet::new();
+    while let Some((file_index, section_index)) = worklist.pop() {
+        if !live_sections.insert((file_index, section_index)) {
+            continue;
+        }
+        let obj = &objs[file_index].1;
+        let section = obj.section_by_index(object::SectionIndex(section_index))?;
+        if section.name()? == ".eh_frame" {
+            // .eh_frame references every function, do not keep them alive
+            continue;
+        }
+        for (_offset, relocation) in section.relocations() {
+            if let object::RelocationTarget::Symbol(symbol_id) = relocation.target() {
+                let symbol = obj.symbol_by_index(symbol_id)?;
+                if symbol.is_local() {
+                    if let Some(target_index) = symbol.section_index() {
+                        worklist.push((file_index, target_index.0));
+                    }
+                } else if let Some(target) = global_symbols.get(symbol.name()?) {
+                    worklist.push(*target);
+                }
+            }
+        }
+    }
+    Ok(live_sections)
+}
+
+/// Report a warning to the user, unless --quiet
+fn user_warning(opt: &Opt, message: &str) {
+    warn!("{}", message);
+    if !opt.quiet {
+        eprintln!("warning: {}", message);
+    }
+}
+
+/// Index in .dynsym of a symbol bound by the dynamic linker: imported ones,
+/// and preemptible ones of shared libraries unless -Bsymbolic binds them
+/// locally. None if the symbol binds locally, so only the load address is
+/// unknown at link time
+fn dynamic_symbol_index(
+    opt: &Opt,
+    symbols: &BTreeMap<String, Symbol>,
+    plt_dynamic_symbols: &[DynamicSymbol],
+    dynamic_symbols: &[DynamicSymbol],
+    name: &str,
+) -> Option<u32> {
+    let symbol = symbols.get(name)?;
+    let position = |dynamic_symbols: &[DynamicSymbol]| {
+        dynamic_symbols
+            .iter()
+            .position(|sym| sym.name == name)
+            .map(|idx| idx as u32)
+    };
+    if symbol.is_plt {
+        position(plt_dynamic_symbols).map(|idx| idx + 1)
+    } else if opt.shared && symbol.is_global && !symbol.is_hidden && !opt.symbolic {
+        position(dynamic_symbols).map(|idx| idx + 1 + plt_dynamic_symbols.len() as u32)
+    } else {
+        None
+    }
+}
+
+/// Sort dynamic symbols by gnu hash bucket
+fn sort_dynamic_symbols(dynamic_symbols: &mut [DynamicSymbol]) {
+    let bucket_count = dynamic_symbols.len();
+    dynamic_symbols.sort_by_key(|sym| {
+        let hash = object::elf::gnu_hash(sym.name.as_bytes());
+        hash % bucket_count as u32
+    });
+}
+
 #[derive(Debug, Clone)]
 pub struct ObjectFile {
     pub name: String,
     /// --as-needed
     pub as_needed: bool,
+    /// index of the enclosing --start-group and --end-group
+    pub group: Option<usize>,
     pub content: Vec<u8>,
 }
 
 // we want our own Relocation & RelocationTarget struct for easier handling
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum RelocationTarget {
     // relocation against section with additional offset
     Section((String, u64)),
@@ -87,6 +572,8 @@ pub struct Relocation {
     encoding: object::RelocationEncoding,
     size: u8,
     addend: i64,
+    // elf relocation type, for relocations without generic kind
+    r_type: u32,
     target: RelocationTarget,
 }
 
@@ -102,10 +589,24 @@ pub struct Symbol {
     symbol_name_dynamic_string_id: Option<StringId>,
     // local or global
     is_global: bool,
-    // a plt symbol to dynamic library
+    // bound by the dynamic linker, in .plt if called
     is_plt: bool,
+    // size of the symbol, st_size
+    sym_size: u64,
+    // STV_HIDDEN, not exported
+    is_hidden: bool,
+    // STT_GNU_IFUNC: the symbol is a resolver returning the actual address
+    is_ifunc: bool,
+    // --emit-stub-syms: name@plt of a PLT entry, STT_FUNC
+    is_stub: bool,
 }
 
+// section name of absolute symbols, whose offset is the address
+const ABSOLUTE_SECTION: &str = "*ABS*";
+
+// maximum length of a path including the terminating NUL on Linux
+const PATH_MAX: usize = 4096;
+
 #[derive(Debug, Clone)]
 pub struct DynamicSymbol {
     name: String,
@@ -122,14 +623,52 @@ pub struct OutputSection {
     pub is_executable: bool,
     pub is_writable: bool,
     pub is_bss: bool,
+    pub is_tls: bool,
+    // maximum alignment of input sections
+    pub align: u64,
+    // position in SECTIONS of linker script
+    pub script_order: Option<usize>,
+    // index of OVERLAY in linker script
+    pub overlay: Option<usize>,
+    // byte in alignment gaps between input sections, zero by default
+    pub fill: Option<u8>,
+    // placed after a gap left by assigning the location counter, filled in
+    // the file unless an address gap
+    pub after_location_gap: bool,
+    // input sections copied here, for the link map
+    pub inputs: Vec<InputSection>,
+    // AT(lma) in linker script
+    pub load_expression: Option<Expression>,
+    // load memory address, where the section is stored: differs from its
+    // address for OVERLAY and AT(lma)
+    pub lma: u64,
     // indices in output ELF
     pub section_index: Option<SectionIndex>,
     pub name_string_id: Option<StringId>,
+    // debug section content is compressed after an Elf64_Chdr header
+    pub is_compressed: bool,
+}
+
+/// Contribution of an input section to an output section
+#[derive(Debug)]
+pub struct InputSection {
+    pub name: String,
+    pub file_name: String,
+    // range of offsets in output section
+    pub start: u64,
+    pub end: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct DynamicRelocation {
+    // r_offset is relative to this output section
+    pub section_name: String,
+    pub rel: Rel,
 }
 
 #[derive(Default, Debug)]
 pub struct OutputRelocationSection {
-    pub relocations: Vec<Rel>,
+    pub relocations: Vec<DynamicRelocation>,
     // offset from ELF load address
     pub offset: u64,
     // indices in output ELF
@@ -139,19 +678,31 @@ pub struct OutputRelocationSection {
 #[derive(Default, Debug)]
 pub struct Needed {
     pub name: String,
+    // dynamic symbols defined by the library
+    pub symbols: Vec<String>,
+    // --as-needed
+    pub as_needed: bool,
     // indices in output ELF
     pub name_string_id: Option<StringId>,
 }
 
 struct Linker<'a> {
     opt: Opt,
+    // -T script
+    script: Script,
     files: Vec<ObjectFile>,
 
     // section name => section
     output_sections: BTreeMap<String, OutputSection>,
+    // debug sections, not loaded: section name => section
+    debug_sections: BTreeMap<String, OutputSection>,
 
     // symbol table: symbol name => symbol
     symbols: BTreeMap<String, Symbol>,
+    // absolute symbols whose values are evaluated after layout
+    symbol_expressions: Vec<(String, Expression)>,
+    // symbols at the ELF header, whose values are the load address
+    header_symbols: Vec<String>,
 
     // dynamic symbols are saved in two parts:
     // plt dynamic symbols that are UNDEF
@@ -166,6 +717,8 @@ struct Linker<'a> {
     writer: Writer<'a>,
 
     load_address: u64,
+    // where the location counter skips pages, in file order
+    address_gaps: Vec<segment::AddressGap>,
 
     // dynamic, dynsym, dynstr, hash, gnu_hash
     dynamic_section_index: SectionIndex,
@@ -177,6 +730,10 @@ struct Linker<'a> {
     gnu_hash_section_offset: u64,
     dynamic_entries_count: usize,
     soname_dynamic_string_index: Option<StringId>,
+    rpath_dynamic_string_index: Option<StringId>,
+
+    // file length mapped by PT_LOAD segments, debug sections follow
+    loaded_len: u64,
 
     // program header offset & len
     phdr_offset: usize,
@@ -184,29 +741,87 @@ struct Linker<'a> {
 
     // dynamically link against shared libraries
     dynamic_link: bool,
+    // emit .dynamic and dynamic symbols: shared, dynamically linked or
+    // static pie
+    dynamic_section: bool,
     needed: Vec<Needed>,
 
     // output relocations
     output_relocations: BTreeMap<String, OutputRelocationSection>,
+    // -z combreloc: index of the first R_X86_64_JUMP_SLOT in .rela.dyn
+    combined_plt_relocations: Option<usize>,
+
+    // thread local storage: symbol name => offset of (module, offset) pair in .got
+    tls_gd_got_offsets: BTreeMap<String, u64>,
+    // offset of (module, 0) pair in .got shared by local dynamic accesses
+    tls_ld_got_offset: Option<u64>,
+    // symbol name => offset of (resolver, argument) descriptor in .got
+    tls_desc_got_offsets: BTreeMap<String, u64>,
+    // symbol name => offset of its offset from thread pointer in .got
+    tls_ie_got_offsets: BTreeMap<String, u64>,
+    // relocation target => offset of its address in .got
+    got_offsets: BTreeMap<RelocationTarget, u64>,
+
+    // address ranges collected from input .debug_aranges
+    debug_aranges: Vec<debug::DebugArange>,
 }
 
 impl<'a> Linker<'a> {
     fn link(opt: &Opt) -> anyhow::Result<()> {
         info!("Link with options: {opt:?}");
+        if opt.only_keep_debug {
+            // debug sections are not copied to output yet
+            bail!("--only-keep-debug is unsupported");
+        }
+        // no zstd encoder is available, only zlib is supported
+        if opt.compress_debug_sections == CompressDebugSections::Zstd {
+            bail!("--compress-debug-sections=zstd is unsupported");
+        }
 
-        let opt = path_resolution(opt)?;
+        let script = match &opt.linker_script {
+            Some(path) => {
+                let script = read_script(path)?;
+                info!("Linker script: {script:?}");
+                script
+            }
+            None => Script::default(),
+        };
+        let mut opt = path_resolution(&script.apply(opt))?;
         info!("Options after path resolution: {opt:?}");
+        if opt.soname.is_some() && !opt.shared {
+            // DT_SONAME only names shared libraries
+            let message =
+                "SONAME set but not producing a shared library; SONAME will have no effect";
+            user_warning(&opt, message);
+            opt.soname = None;
+        }
+        if let Some(dynamic_linker) = &opt.dynamic_linker {
+            // the kernel refuses to execute with "Exec format error" if
+            // .interp does not fit in PATH_MAX with the terminating NUL
+            if dynamic_linker.len() >= PATH_MAX {
+                bail!(
+                    "Dynamic linker path is too long: {} bytes, at most {} allowed",
+                    dynamic_linker.len(),
+                    PATH_MAX - 1
+                );
+            }
+        }
 
         let mut arena = Arena::new();
         let mut buffer = vec![];
         let mut linker = Linker {
             opt,
+            script,
             files: vec![],
             output_sections: BTreeMap::new(),
+            debug_sections: BTreeMap::new(),
             symbols: BTreeMap::new(),
+            symbol_expressions: vec![],
+            header_symbols: vec![],
             section_address: BTreeMap::new(),
             writer: Writer::new(object::Endianness::Little, true, &mut buffer),
             load_address: 0,
+            address_gaps: vec![],
             dynamic_section_index: SectionIndex(0),
             dynamic_section_offset: 0,
             dynamic_entries_count: 0,
@@ -216,30 +831,83 @@ impl<'a> Linker<'a> {
             hash_section_offset: 0,
             gnu_hash_section_offset: 0,
             soname_dynamic_string_index: None,
+            rpath_dynamic_string_index: None,
+            loaded_len: 0,
             phdr_offset: 0,
             phdr_len: 0,
             dynamic_link: false,
+            dynamic_section: false,
             needed: vec![],
             output_relocations: BTreeMap::new(),
+            combined_plt_relocations: None,
+            tls_gd_got_offsets: BTreeMap::new(),
+            tls_ld_got_offset: None,
+            tls_desc_got_offsets: BTreeMap::new(),
+            tls_ie_got_offsets: BTreeMap::new(),
+            got_offsets: BTreeMap::new(),
+            debug_aranges: vec![],
             dynamic_symbols: vec![],
             plt_dynamic_symbols: vec![],
         };
         linker.read_files()?;
         linker.parse_files()?;
+        linker.provide_symbols()?;
+        linker.resolve_undefined_symbols()?;
+        linker.generate_interp();
+        linker.remove_unused_needed();
         linker.generate_plt()?;
+        linker.generate_tls()?;
+        linker.generate_got()?;
+        linker.generate_relative()?;
+        linker.generate_iplt()?;
+        if linker.opt.combreloc {
+            linker.combine_relocations();
+        }
         linker.reserve(&mut arena)?;
+        linker.assign_addresses()?;
+        linker.define_header_symbols();
+        linker.evaluate_symbols()?;
         linker.relocate()?;
+        linker.apply_relative();
+        if !linker.opt.no_check_sections {
+            linker.check_sections()?;
+        }
+        linker.check_memory_regions()?;
+        linker.check_assertions()?;
+        linker.check_debug_aranges();
+        linker.reserve_debug_sections()?;
         linker.write()?;
+        if let Some(map_file) = &linker.opt.map_file {
+            linker.write_map(map_file)?;
+        }
+
+        // memory footprint from load address to the end of the last section
+        let vma_size = linker
+            .output_sections
+            .iter()
+            .map(|(name, output_section)| {
+                linker.section_address[name] + output_section.content.len() as u64
+            })
+            .max()
+            .map_or(0, |end| end - linker.load_address);
 
         // done, save to file
-        let output = linker.opt.output.as_ref().unwrap();
+        let output = linker.opt.output.clone().unwrap();
+        let print_output_size = linker.opt.print_output_size && !linker.opt.quiet;
         info!("Writing to executable {}", output);
-        std::fs::write(output, buffer)?;
+        let file_size = buffer.len();
+        std::fs::write(&output, buffer)?;
+        if print_output_size {
+            eprintln!(
+                "linked {}: {} bytes on disk, {} bytes in memory",
+                output, file_size, vma_size
+            );
+        }
 
         // make executable
-        let mut perms = std::fs::metadata(output)?.permissions();
+        let mut perms = std::fs::metadata(&output)?.permissions();
         perms.set_mode(0o755);
-        std::fs::set_permissions(output, perms)?;
+        std::fs::set_permissions(&output, perms)?;
 
         Ok(())
     }
@@ -248,6 +916,8 @@ impl<'a> Linker<'a> {
         let Linker { opt, files, .. } = self;
 
         // read files
+        let mut groups = 0;
+        let mut group = None;
         for obj_file in &opt.obj_file {
             match obj_file {
                 ObjectFileOpt::File(file_opt) => {
@@ -255,65 +925,193 @@ impl<'a> Linker<'a> {
                     files.push(ObjectFile {
                         name: file_opt.name.clone(),
                         as_needed: file_opt.as_needed,
+                        group,
                         content: std::fs::read(&file_opt.name)
                             .context(format!("Reading file {}", file_opt.name))?,
                     });
                 }
                 ObjectFileOpt::Library(_) => unreachable!("Path resolution is not working"),
-                ObjectFileOpt::StartGroup => warn!("--start-group unhandled"),
-                ObjectFileOpt::EndGroup => warn!("--end-group unhandled"),
+                ObjectFileOpt::StartGroup => {
+                    if group.is_some() {
+                        bail!("May not nest groups");
+                    }
+                    group = Some(groups);
+                    groups += 1;
+                }
+                ObjectFileOpt::EndGroup => {
+                    if group.take().is_none() {
+                        bail!("Group ended before it began");
+                    }
+                }
             }
         }
 
         Ok(())
     }
 
+    /// Input files are processed in command line order, so output only
+    /// depends on the order of inputs: input sections are appended to their
+    /// output section in that order, SORT_BY_NAME and SORT_BY_ALIGNMENT only
+    /// reorder sections within a file, and if a global symbol is defined by
+    /// several files, the first definition wins unless it is weak
     fn parse_files(&mut self) -> anyhow::Result<()> {
         let Linker {
             opt,
+            script,
             files,
             output_sections,
+            debug_sections,
             symbols,
             dynamic_symbols,
             plt_dynamic_symbols,
+            debug_aranges,
             ..
         } = self;
 
         // parse files and resolve symbols
         let mut objs = vec![];
-        for file in files {
+        // symbols defined and referenced by files so far, decide which
+        // archive members are extracted
+        let mut defined = BTreeSet::new();
+        let mut undefined = BTreeSet::new();
+        // global symbols defined so far, and whether the definition is weak
+        let mut global_definitions: BTreeMap<String, bool> = BTreeMap::new();
+        // linked with --as-needed
+        let as_needed: BTreeSet<String> = files
+            .iter()
+            .filter(|file| file.as_needed)
+            .map(|file| file.name.clone())
+            .collect();
+        if !opt.shared || opt.entry_symbol.is_some() {
+            undefined.insert(
+                opt.entry_symbol
+                    .clone()
+                    .unwrap_or("_start".to_string())
+                    .into_bytes(),
+            );
+        }
+        // members extracted from archives: index in files => member offsets
+        let mut extracted = BTreeMap::new();
+        for (file_index, file) in files.iter().enumerate() {
             info!("Parsing {}", file.name);
             if file.name.ends_with(".a") {
                 // archive
-                let ar = object::read::archive::ArchiveFile::parse(file.content.as_slice())
-                    .context(format!("Parsing file {} as archive", file.name))?;
-                for member in ar.members() {
-                    let member = member?;
-                    let name = format!("{}({})", file.name, std::str::from_utf8(member.name())?);
-                    info!("Parsing {}", name);
-                    let obj = object::File::parse(member.data(file.content.as_slice())?)
-                        .context(format!("Parsing file {} as object", name))?;
-                    objs.push((name, obj));
-                }
+                extract_archive_members(
+                    file,
+                    extracted.entry(file_index).or_default(),
+                    false,
+                    &mut defined,
+                    &mut undefined,
+                    &mut objs,
+                )?;
             } else {
                 // object
                 let obj = object::File::parse(file.content.as_slice())
                     .context(format!("Parsing file {} as object", file.name))?;
+                collect_symbols(&obj, &mut defined, &mut undefined)?;
                 objs.push((file.name.clone(), obj));
             }
+
+            // at the end of a group, scan its archives again until no more
+            // members are extracted, for circular references between them
+            let Some(group) = file.group else {
+                continue;
+            };
+            if files.get(file_index + 1).and_then(|next| next.group) == Some(group) {
+                continue;
+            }
+            loop {
+                let mut resolved = false;
+                for (index, archive) in files.iter().enumerate() {
+                    if archive.group != Some(group) || !archive.name.ends_with(".a") {
+                        continue;
+                    }
+                    resolved |= extract_archive_members(
+                        archive,
+                        extracted.entry(index).or_default(),
+                        true,
+                        &mut defined,
+                        &mut undefined,
+                        &mut objs,
+                    )?;
+                }
+                if !resolved {
+                    break;
+                }
+            }
         }
 
-        for (name, obj) in objs {
+        // CIEs in .eh_frame shared by all objects
+        let mut eh_frame_cies = BTreeMap::new();
+
+        // GNU_PROPERTY_X86_FEATURE_1_AND of all objects, AND-ed together
+        let mut x86_feature_1_and = Some(u32::MAX);
+
+        let live_sections = if opt.gc_sections {
+            Some(gc_sections(opt, script, &objs)?)
+        } else {
+            None
+        };
+
+        // all inputs must match the target of OUTPUT_FORMAT, -m emulation or
+        // the first file: target => where it comes from
+        let emulation_target = opt.emulation.as_deref().map(emulation_target).transpose()?;
+        let mut expected: Option<(Target, String)> = match (&opt.output_format, &opt.emulation) {
+            (Some(format), _) => match EMULATIONS.iter().find(|(_, name, _)| name == format) {
+                None => bail!("Unsupported output format {}", format),
+                Some((_, _, target)) if *target != OUTPUT_TARGET => bail!(
+                    "Unsupported output format {}: only elf64-x86-64 is supported",
+                    format
+                ),
+                Some((_, _, target)) => Some((*target, format!("OUTPUT_FORMAT({})", format))),
+            },
+            (None, Some(emulation)) => {
+                emulation_target.map(|(_, target)| (target, format!("-m {}", emulation)))
+            }
+            (None, None) => None,
+        };
+
+        for (file_index, (name, obj)) in objs.into_iter().enumerate() {
             let _span = info_span!("file", name).entered();
+            let target = (obj.architecture(), obj.is_64(), obj.endianness());
+            match &expected {
+                None if target != OUTPUT_TARGET => bail!(
+                    "Unsupported target: {} is {}, only {} is supported",
+                    name,
+                    target_name(target),
+                    target_name(OUTPUT_TARGET)
+                ),
+                None => expected = Some((target, name.clone())),
+                Some((expected_target, source)) if *expected_target != target => bail!(
+                    "Architecture mismatch: {} is {}, expected {} of {}",
+                    name,
+                    target_name(target),
+                    target_name(*expected_target),
+                    source
+                ),
+                Some(_) => {}
+            }
+            // garbage collected by --gc-sections
+            let is_removed = |section_index: object::SectionIndex| {
+                live_sections
+                    .as_ref()
+                    .is_some_and(|live| !live.contains(&(file_index, section_index.0)))
+            };
             match obj {
                 object::File::Elf64(elf) => {
+                    // EV_CURRENT is the only version defined by the ELF spec
+                    let e_version = elf.elf_header().e_version.get(elf.endian());
+                    if e_version != EV_CURRENT.into() {
+                        bail!("Unsupported ELF version {} of file {}", e_version, name);
+                    }
                     if elf.kind() == ObjectKind::Dynamic {
                         // linked against dynamic library
                         self.dynamic_link = true;
-                        self.needed.push(Needed {
+                        let mut needed = Needed {
                             name: name.clone(),
-                            name_string_id: None,
-                        });
+                            as_needed: as_needed.contains(&name),
+                            ..Needed::default()
+                        };
 
                         // walk through its dynamic symbols
                         for symbol in elf.dynamic_symbols() {
@@ -323,110 +1121,305 @@ impl<'a> Linker<'a> {
                                 plt_dynamic_symbols.push(DynamicSymbol {
                                     name: name.to_string(),
                                 });
+                                needed.symbols.push(name.to_string());
                             }
                         }
+                        self.needed.push(needed);
                         continue;
                     }
 
                     // collect section sizes prior to this object
                     let section_sizes: BTreeMap<String, u64> = output_sections
                         .iter()
+                        .chain(debug_sections.iter())
                         .map(|(key, value)| (key.clone(), value.content.len() as u64))
                         .collect();
 
+                    let file_name = &name;
+                    let mut obj_x86_feature_1_and = None;
+                    let mut debug_aranges_sections = vec![];
+                    // dropped by --exclude-section
+                    let is_excluded = |section_name: &str| {
+                        opt.exclude_sections
+                            .iter()
+                            .any(|pattern| glob_match(pattern, section_name))
+                    };
+
+                    // where input sections go:
+                    // section index => (output section name, offset in output section)
+                    let mut placements: BTreeMap<usize, (String, u64)> = BTreeMap::new();
+                    // section index => section name, of sections in /DISCARD/
+                    // or dropped by --exclude-section
+                    let mut discarded: BTreeMap<usize, String> = BTreeMap::new();
+                    // (section index, name, output section name, sort order)
+                    // in order of placement
+                    let mut placed: Vec<(object::SectionIndex, &str, &str, Option<SortOrder>)> =
+                        vec![];
                     for section in elf.sections() {
                         let name = section.name()?;
-                        if !name.is_empty() {
-                            let _span = info_span!("section", name).entered();
-                            let data = section.data()?;
-                            let (is_executable, is_writable) = match section.flags() {
-                                object::SectionFlags::Elf { sh_flags } => {
-                                    if ((sh_flags as u32) & object::elf::SHF_ALLOC) == 0 {
-                                        // non-alloc, skip
+                        if is_excluded(name) {
+                            info!("Excluding section {}", name);
+                            discarded.insert(section.index().0, name.to_string());
+                            continue;
+                        }
+                        if opt.strip_debug && is_debug_section(name) {
+                            info!("Stripping debug section {}", name);
+                            continue;
+                        }
+                        if name == ".note.gnu.property" {
+                            // properties are combined instead of concatenated
+                            obj_x86_feature_1_and = property::x86_feature_1_and(&section)?;
+                            continue;
+                        }
+                        if name == ".debug_aranges" {
+                            // parsed after placements are known
+                            debug_aranges_sections.push(section.index());
+                        }
+                        if name.is_empty() {
+                            continue;
+                        }
+                        match section.flags() {
+                            object::SectionFlags::Elf { sh_flags } => {
+                                if ((sh_flags as u32) & object::elf::SHF_ALLOC) == 0 {
+                                    // non-alloc: only debug sections are kept,
+                                    // in output sections of the same name
+                                    let sh_type =
+                                        section.elf_section_header().sh_type.get(elf.endian());
+                                    if !is_debug_section(name) || sh_type != SHT_PROGBITS {
                                         continue;
-                                    } else {
-                                        (
-                                            ((sh_flags as u32) & object::elf::SHF_EXECINSTR) != 0,
-                                            ((sh_flags as u32) & object::elf::SHF_WRITE) != 0,
-                                        )
                                     }
+                                    if script.output_section_name(file_name, name) == Some(DISCARD)
+                                    {
+                                        info!("Discarding section {}", name);
+                                        discarded.insert(section.index().0, name.to_string());
+                                        continue;
+                                    }
+                                    placed.push((section.index(), name, name, None));
+                                    continue;
                                 }
-                                _ => unimplemented!(),
-                            };
-
-                            // copy to output
-                            let out = output_sections
-                                .entry(name.to_string())
-                                .or_insert_with(OutputSection::default);
-                            out.name = name.to_string();
-                            out.content.extend(data);
-                            if (data.len() as u64) < section.size() {
-                                // handle bss, extend with zero
-                                out.content.resize(
-                                    out.content.len() - data.len() + section.size() as usize,
-                                    0,
+                            }
+                            _ => unimplemented!(),
+                        };
+
+                        if is_removed(section.index()) {
+                            info!("Removing unused section {}", name);
+                            if opt.print_gc_sections {
+                                eprintln!(
+                                    "removing unused section '{}' in file '{}'",
+                                    name, file_name
                                 );
                             }
-                            out.is_executable |= is_executable;
-                            out.is_writable |= is_writable;
-                            out.is_bss |= section.kind() == object::SectionKind::UninitializedData;
-                            for (offset, relocation) in section.relocations() {
-                                match relocation.target() {
-                                    object::RelocationTarget::Symbol(symbol_id) => {
-                                        let symbol = elf.symbol_by_index(symbol_id)?;
-                                        if symbol.kind() == object::SymbolKind::Section {
-                                            // relocation to a section
-                                            let section_index = symbol.section_index().unwrap();
-                                            let target_section =
-                                                elf.section_by_index(section_index)?;
-                                            let target_section_name = target_section.name()?;
-                                            info!(
-                                                "Found relocation targeting section {}",
-                                                target_section_name
-                                            );
-
-                                            out.relocations.push(Relocation {
-                                                offset: offset
-                                                    + *section_sizes.get(name).unwrap_or(&0),
-                                                kind: relocation.kind(),
-                                                encoding: relocation.encoding(),
-                                                size: relocation.size(),
-                                                addend: relocation.addend(),
-                                                target: RelocationTarget::Section((
-                                                    target_section_name.to_string(),
-                                                    // record current size of section, because there can be existing content in the section from other object file
-                                                    *section_sizes
-                                                        .get(target_section_name)
-                                                        .unwrap_or(&0),
-                                                )),
-                                            });
-                                        } else {
-                                            // relocation to a symbol
-                                            let symbol_name = symbol.name()?;
-                                            info!(
-                                                "Found relocation targeting symbol {}",
-                                                symbol_name
-                                            );
-
-                                            out.relocations.push(Relocation {
-                                                offset: offset
-                                                    + *section_sizes.get(name).unwrap_or(&0),
-                                                kind: relocation.kind(),
-                                                encoding: relocation.encoding(),
-                                                size: relocation.size(),
-                                                addend: relocation.addend(),
-                                                target: RelocationTarget::Symbol(
-                                                    symbol_name.to_string(),
-                                                ),
-                                            });
+                            continue;
+                        }
+
+                        let output_name = match script.output_section_name(file_name, name) {
+                            Some(DISCARD) => {
+                                info!("Discarding section {}", name);
+                                discarded.insert(section.index().0, name.to_string());
+                                continue;
+                            }
+                            Some(output_name) => output_name,
+                            None => name,
+                        };
+                        let sort = script.input_section_sort(file_name, name);
+                        placed.push((section.index(), name, output_name, sort));
+                    }
+
+                    // sections matched by SORT_BY_NAME or SORT_BY_ALIGNMENT
+                    // are reordered among themselves, in each file
+                    let mut sorted: BTreeMap<(&str, SortOrder), Vec<usize>> = BTreeMap::new();
+                    for (position, (_, _, output_name, sort)) in placed.iter().enumerate() {
+                        if let Some(sort) = sort {
+                            sorted
+                                .entry((output_name, *sort))
+                                .or_default()
+                                .push(position);
+                        }
+                    }
+                    for ((_, sort), positions) in sorted {
+                        let mut group: Vec<_> =
+                            positions.iter().map(|position| placed[*position]).collect();
+                        match sort {
+                            SortOrder::Name => group.sort_by_key(|(_, name, _, _)| *name),
+                            SortOrder::Alignment => group.sort_by_key(|(index, _, _, _)| {
+                                std::cmp::Reverse(elf.section_by_index(*index).unwrap().align())
+                            }),
+                        }
+                        for (position, section) in positions.into_iter().zip(group) {
+                            placed[position] = section;
+                        }
+                    }
+
+                    let mut output_sizes = section_sizes.clone();
+                    for (index, _, output_name, _) in &placed {
+                        let section = elf.section_by_index(*index)?;
+                        // keep alignment of input section in output section
+                        let size = output_sizes.entry(output_name.to_string()).or_insert(0);
+                        let start = size.next_multiple_of(section.align().max(1));
+                        placements.insert(index.0, (output_name.to_string(), start));
+                        *size = start + section.size();
+                    }
+
+                    for (index, _, _, _) in &placed {
+                        let section = elf.section_by_index(*index)?;
+                        let (output_name, start) = &placements[&index.0];
+                        let name = section.name()?;
+                        let _span = info_span!("section", name).entered();
+                        let data = section.data()?;
+                        let (is_executable, is_writable, is_tls, is_debug) = match section.flags() {
+                            object::SectionFlags::Elf { sh_flags } => (
+                                ((sh_flags as u32) & object::elf::SHF_EXECINSTR) != 0,
+                                ((sh_flags as u32) & object::elf::SHF_WRITE) != 0,
+                                ((sh_flags as u32) & object::elf::SHF_TLS) != 0,
+                                ((sh_flags as u32) & object::elf::SHF_ALLOC) == 0,
+                            ),
+                            _ => unimplemented!(),
+                        };
+                        if output_name != name {
+                            info!("Placing section {} in {}", name, output_name);
+                        }
+
+                        // copy to output
+                        let out = if is_debug {
+                            debug_sections.entry(output_name.clone())
+                        } else {
+                            output_sections.entry(output_name.clone())
+                        }
+                        .or_insert_with(OutputSection::default);
+                        out.name = output_name.clone();
+                        out.fill = script.fill(output_name);
+                        // pad up to the placement
+                        let fill = out.fill.unwrap_or(0);
+                        out.content.resize(*start as usize, fill);
+                        let content_start = out.content.len();
+                        let relocations_start = out.relocations.len();
+                        out.content.extend(data);
+                        if (data.len() as u64) < section.size() {
+                            // handle bss, extend with zero
+                            out.content.resize(
+                                out.content.len() - data.len() + section.size() as usize,
+                                0,
+                            );
+                        }
+                        out.inputs.push(InputSection {
+                            name: name.to_string(),
+                            file_name: file_name.clone(),
+                            start: *start,
+                            end: *start + section.size(),
+                        });
+                        out.is_executable |= is_executable;
+                        out.is_writable |= is_writable;
+                        out.is_bss |= section.kind() == object::SectionKind::UninitializedData;
+                        out.is_tls |= is_tls;
+                        out.align = out.align.max(section.align());
+                        for (offset, relocation) in section.relocations() {
+                            let r_type = match relocation.flags() {
+                                object::RelocationFlags::Elf { r_type } => r_type,
+                                _ => unimplemented!(),
+                            };
+                            match relocation.target() {
+                                object::RelocationTarget::Symbol(symbol_id) => {
+                                    let symbol = elf.symbol_by_index(symbol_id)?;
+                                    if symbol.is_local()
+                                        && symbol.section_index().is_some_and(is_removed)
+                                    {
+                                        // only .eh_frame can reference removed sections
+                                        info!("Skipping relocation to removed section");
+                                        continue;
+                                    }
+                                    if let Some(discarded_name) = symbol
+                                        .section_index()
+                                        .and_then(|index| discarded.get(&index.0))
+                                    {
+                                        if name == ".eh_frame" || is_debug_section(name) {
+                                            // frames and debug info of discarded
+                                            // code are unused
+                                            info!("Skipping relocation to discarded section");
+                                            continue;
                                         }
+                                        bail!(
+                                            "{} referenced in section {} of {}: defined in discarded section",
+                                            discarded_name,
+                                            name,
+                                            file_name
+                                        );
                                     }
-                                    _ => unimplemented!(),
-                                };
-                            }
+                                    if symbol.kind() == object::SymbolKind::Section {
+                                        // relocation to a section
+                                        let section_index = symbol.section_index().unwrap();
+                                        let target_section = elf.section_by_index(section_index)?;
+                                        let target_section_name = target_section.name()?;
+                                        info!(
+                                            "Found relocation targeting section {}",
+                                            target_section_name
+                                        );
+
+                                        out.relocations.push(Relocation {
+                                            offset: offset + start,
+                                            kind: relocation.kind(),
+                                            encoding: relocation.encoding(),
+                                            size: relocation.size(),
+                                            addend: relocation.addend(),
+                                            r_type,
+                                            // record where the section is placed, because there can be existing content in the output section from other input sections
+                                            target: RelocationTarget::Section(
+                                                placements
+                                                    .get(&section_index.0)
+                                                    .cloned()
+                                                    .unwrap_or((
+                                                        target_section_name.to_string(),
+                                                        *section_sizes
+                                                            .get(target_section_name)
+                                                            .unwrap_or(&0),
+                                                    )),
+                                            ),
+                                        });
+                                    } else {
+                                        // relocation to a symbol
+                                        let symbol_name = symbol.name()?;
+                                        info!("Found relocation targeting symbol {}", symbol_name);
+
+                                        out.relocations.push(Relocation {
+                                            offset: offset + start,
+                                            kind: relocation.kind(),
+                                            encoding: relocation.encoding(),
+                                            size: relocation.size(),
+                                            addend: relocation.addend(),
+                                            r_type,
+                                            target: RelocationTarget::Symbol(
+                                                symbol_name.to_string(),
+                                            ),
+                                        });
+                                    }
+                                }
+                                _ => unimplemented!(),
+                            };
                         }
+
+                        if name == ".eh_frame" {
+                            eh_frame::dedup_cies(
+                                out,
+                                content_start,
+                                relocations_start,
+                                &mut eh_frame_cies,
+                            )?;
+                        }
+                    }
+
+                    for section_index in debug_aranges_sections {
+                        let section = elf.section_by_index(section_index)?;
+                        debug_aranges.extend(debug::parse_debug_aranges(
+                            &elf,
+                            &section,
+                            &placements,
+                        )?);
                     }
 
+                    // feature is only kept if every object supports it
+                    x86_feature_1_and = x86_feature_1_and
+                        .zip(obj_x86_feature_1_and)
+                        .map(|(all, obj)| all & obj);
+
                     for symbol in elf.symbols() {
                         if !symbol.is_undefined()
                             && symbol.kind() != object::SymbolKind::Section
@@ -435,25 +1428,65 @@ impl<'a> Linker<'a> {
                             let name = symbol.name()?;
                             match symbol.section() {
                                 object::SymbolSection::Section(section_index) => {
-                                    let section = elf.section_by_index(section_index)?;
-                                    let section_name = section.name()?;
+                                    // dropped input section
+                                    let Some((section_name, start)) =
+                                        placements.get(&section_index.0)
+                                    else {
+                                        continue;
+                                    };
+                                    if debug_sections.contains_key(section_name) {
+                                        // debug sections have no address
+                                        continue;
+                                    }
+                                    if symbol.is_global() {
+                                        // earlier files take precedence,
+                                        // except over weak definitions
+                                        match global_definitions.get(name) {
+                                            Some(is_weak) if !*is_weak || symbol.is_weak() => {
+                                                info!(
+                                                    "Ignoring symbol {}, already defined by an earlier file",
+                                                    name
+                                                );
+                                                continue;
+                                            }
+                                            _ => {}
+                                        }
+                                        global_definitions
+                                            .insert(name.to_string(), symbol.is_weak());
+                                    }
                                     info!("Defining symbol {} from section {}", name, section_name);
-                                    // offset: consider existing section content from other files
-                                    let offset = symbol.address()
-                                        + section_sizes.get(section_name).unwrap_or(&0);
+                                    // offset: consider existing section content from other input sections
+                                    let offset = symbol.address() + start;
+                                    let is_ifunc = matches!(
+                                        symbol.flags(),
+                                        object::SymbolFlags::Elf { st_info, .. }
+                                            if st_info & 0xf == STT_GNU_IFUNC
+                                    );
+                                    let is_hidden = matches!(
+                                        symbol.flags(),
+                                        object::SymbolFlags::Elf { st_other, .. }
+                                            if st_other & 0x3 == STV_HIDDEN
+                                    );
                                     symbols.insert(
                                         name.to_string(),
                                         Symbol {
-                                            section_name: section_name.to_string(),
+                                            section_name: section_name.clone(),
                                             offset,
                                             symbol_name_string_id: None,
                                             symbol_name_dynamic_string_id: None,
                                             is_global: symbol.is_global(),
                                             is_plt: false,
+                                            sym_size: symbol.size(),
+                                            is_hidden,
+                                            is_ifunc,
+                                            is_stub: false,
                                         },
                                     );
 
-                                    if symbol.is_global() && opt.shared {
+                                    if symbol.is_global()
+                                        && !is_hidden
+                                        && (opt.shared || opt.export_dynamic)
+                                    {
                                         // export GLOBAL symbols in dynsym
                                         dynamic_symbols.push(DynamicSymbol {
                                             name: name.to_string(),
@@ -473,7 +1506,19 @@ impl<'a> Linker<'a> {
             }
         }
 
-        if opt.shared || self.dynamic_link {
+        if let Some(x86_feature_1_and) = x86_feature_1_and.filter(|features| *features != 0) {
+            info!(
+                "Emitting GNU_PROPERTY_X86_FEATURE_1_AND {:#x}",
+                x86_feature_1_and
+            );
+            output_sections.insert(
+                ".note.gnu.property".to_string(),
+                property::gnu_property_section(x86_feature_1_and),
+            );
+        }
+
+        self.dynamic_section = opt.shared || opt.static_pie || self.dynamic_link;
+        if self.dynamic_section {
             // add _DYNAMIC symbol
             symbols.insert(
                 "_DYNAMIC".to_string(),
@@ -484,34 +1529,38 @@ impl<'a> Linker<'a> {
                     symbol_name_dynamic_string_id: None,
                     is_global: false,
                     is_plt: false,
+                    sym_size: 0,
+                    is_hidden: false,
+                    is_ifunc: false,
+                    is_stub: false,
                 },
             );
         }
 
-        // sort dynamic symbols by gnu hash bucket
-        let bucket_count = dynamic_symbols.len();
-        dynamic_symbols.sort_by_key(|sym| {
-            let hash = object::elf::gnu_hash(sym.name.as_bytes());
-            hash % bucket_count as u32
-        });
+        sort_dynamic_symbols(dynamic_symbols);
+        Ok(())
+    }
 
-        if !opt.shared && self.dynamic_link {
+    /// Dynamically linked executables are started by the dynamic linker
+    /// named in .interp
+    fn generate_interp(&mut self) {
+        if !self.opt.shared && self.dynamic_link {
             let mut interp = OutputSection {
                 name: ".interp".to_string(),
                 ..OutputSection::default()
             };
             interp
                 .content
-                .extend_from_slice(opt.dynamic_linker.as_ref().unwrap().as_bytes());
+                .extend_from_slice(self.opt.dynamic_linker.as_ref().unwrap().as_bytes());
             // NULL terminated string
             interp.content.push(0);
-            output_sections.insert(".interp".to_string(), interp);
+            self.output_sections.insert(".interp".to_string(), interp);
         }
-        Ok(())
     }
 
     fn generate_plt(&mut self) -> anyhow::Result<()> {
         let Linker {
+            opt,
             output_sections,
             symbols,
             plt_dynamic_symbols,
@@ -543,6 +1592,7 @@ impl<'a> Linker<'a> {
                 encoding: object::RelocationEncoding::Generic,
                 size: 32,
                 addend: 8 - 4,
+                r_type: R_X86_64_PC32,
                 target: RelocationTarget::Section((".got.plt".to_string(), 0)),
             });
             // relocation for jmp *.got.plt+16(%rip)
@@ -552,6 +1602,7 @@ impl<'a> Linker<'a> {
                 encoding: object::RelocationEncoding::Generic,
                 size: 32,
                 addend: 16 - 4,
+                r_type: R_X86_64_PC32,
                 target: RelocationTarget::Section((".got.plt".to_string(), 0)),
             });
             output_sections.insert(".plt".to_string(), plt);
@@ -560,6 +1611,8 @@ impl<'a> Linker<'a> {
             assert!(!output_sections.contains_key(".got.plt"));
             let mut got_plt = OutputSection {
                 name: ".got.plt".to_string(),
+                // written by ld.so during lazy binding
+                is_writable: true,
                 ..OutputSection::default()
             };
             got_plt.content.extend(vec![
@@ -577,6 +1630,7 @@ impl<'a> Linker<'a> {
                 encoding: object::RelocationEncoding::Generic,
                 size: 64,
                 addend: 0,
+                r_type: R_X86_64_64,
                 target: RelocationTarget::Section((".dynamic".to_string(), 0)),
             });
             output_sections.insert(".got.plt".to_string(), got_plt);
@@ -591,10 +1645,57 @@ impl<'a> Linker<'a> {
                     symbol_name_dynamic_string_id: None,
                     is_global: false,
                     is_plt: false,
+                    sym_size: 0,
+                    is_hidden: false,
+                    is_ifunc: false,
+                    is_stub: false,
                 },
             );
 
+            // only calls go through .plt, and absolute addresses in
+            // executables, which are fixed at link time
+            let position_independent = opt.shared || opt.pie;
+            let called: BTreeSet<String> = output_sections
+                .values()
+                .flat_map(|output_section| output_section.relocations.iter())
+                .filter(|relocation| match relocation.r_type {
+                    R_X86_64_PLT32 | R_X86_64_PC32 => true,
+                    R_X86_64_64 | R_X86_64_32 | R_X86_64_32S => !position_independent,
+                    _ => false,
+                })
+                .filter_map(|relocation| match &relocation.target {
+                    RelocationTarget::Symbol(name) => Some(name.clone()),
+                    RelocationTarget::Section(_) => None,
+                })
+                .collect();
+            // .rela.plt is referenced by .dynamic even without entries
+            output_relocations
+                .entry(".rela.plt".to_string())
+                .or_default();
+
+            let mut plt_index = 0;
             for (idx, dyn_sym) in plt_dynamic_symbols.iter().enumerate() {
+                if !called.contains(&dyn_sym.name) {
+                    // data is reached through GOT entries and dynamic
+                    // relocations against the symbol
+                    symbols.insert(
+                        dyn_sym.name.clone(),
+                        Symbol {
+                            section_name: ABSOLUTE_SECTION.to_string(),
+                            offset: 0,
+                            symbol_name_string_id: None,
+                            symbol_name_dynamic_string_id: None,
+                            is_global: true,
+                            is_plt: true,
+                            sym_size: 0,
+                            is_hidden: false,
+                            is_ifunc: false,
+                            is_stub: false,
+                        },
+                    );
+                    continue;
+                }
+
                 // redirect the symbol to plt
                 let plt = output_sections.get_mut(".plt").unwrap();
                 let plt_offset = plt.content.len() as u64;
@@ -604,7 +1705,8 @@ impl<'a> Linker<'a> {
                 plt.content.extend(vec![0xff, 0x25, 0x00, 0x00, 0x00, 0x00]);
                 // 68 xx xx xx xx    push index
                 plt.content.push(0x68);
-                plt.content.extend_from_slice(&(idx as u32).to_le_bytes());
+                plt.content
+                    .extend_from_slice(&(plt_index as u32).to_le_bytes());
                 // e9 xx xx xx xx    jmp plt_first_entry
                 plt.content.extend(vec![0xe9, 0x00, 0x00, 0x00, 0x00]);
 
@@ -616,7 +1718,8 @@ impl<'a> Linker<'a> {
                     size: 32,
                     // each got entry: 8 bytes
                     // 24: got header
-                    addend: (idx as i64 * 8 + 24) - 4,
+                    addend: (plt_index as i64 * 8 + 24) - 4,
+                    r_type: R_X86_64_PC32,
                     target: RelocationTarget::Section((".got.plt".to_string(), 0)),
                 });
                 // relocation for jmp plt_first_entry
@@ -626,6 +1729,7 @@ impl<'a> Linker<'a> {
                     encoding: object::RelocationEncoding::Generic,
                     size: 32,
                     addend: 0 - 4,
+                    r_type: R_X86_64_PC32,
                     target: RelocationTarget::Section((".plt".to_string(), 0)),
                 });
 
@@ -642,6 +1746,7 @@ impl<'a> Linker<'a> {
                     encoding: object::RelocationEncoding::Generic,
                     size: 64,
                     addend: plt_offset as i64 + 6, // point to push index
+                    r_type: R_X86_64_64,
                     target: RelocationTarget::Section((".plt".to_string(), 0)),
                 });
 
@@ -650,11 +1755,14 @@ impl<'a> Linker<'a> {
                     .entry(".rela.plt".to_string())
                     .or_default()
                     .relocations
-                    .push(Rel {
-                        r_offset: got_offset,
-                        r_sym: (idx + 1) as u32,
-                        r_type: R_X86_64_JUMP_SLOT,
-                        r_addend: 0,
+                    .push(DynamicRelocation {
+                        section_name: ".got.plt".to_string(),
+                        rel: Rel {
+                            r_offset: got_offset,
+                            r_sym: (idx + 1) as u32,
+                            r_type: R_X86_64_JUMP_SLOT,
+                            r_addend: 0,
+                        },
                     });
 
                 symbols.insert(
@@ -666,18 +1774,443 @@ impl<'a> Linker<'a> {
                         symbol_name_dynamic_string_id: None,
                         is_global: true,
                         is_plt: true,
+                        sym_size: 0,
+                        is_hidden: false,
+                        is_ifunc: false,
+                        is_stub: false,
                     },
                 );
+                plt_index += 1;
+                if opt.emit_stub_syms {
+                    // lets debuggers name calls into the entry
+                    symbols.insert(
+                        format!("{}@plt", dyn_sym.name),
+                        Symbol {
+                            section_name: ".plt".to_string(),
+                            offset: plt_offset,
+                            symbol_name_string_id: None,
+                            symbol_name_dynamic_string_id: None,
+                            is_global: false,
+                            is_plt: false,
+                            sym_size: 16,
+                            is_hidden: false,
+                            is_ifunc: false,
+                            is_stub: true,
+                        },
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Allocate entries in .got for relocations loading addresses from
+    /// them, and define _GLOBAL_OFFSET_TABLE_ if used
+    fn generate_got(&mut self) -> anyhow::Result<()> {
+        let Linker {
+            opt,
+            output_sections,
+            symbols,
+            got_offsets,
+            output_relocations,
+            plt_dynamic_symbols,
+            dynamic_symbols,
+            ..
+        } = self;
+
+        // R_X86_64_GOTPCRELX and R_X86_64_REX_GOTPCRELX of
+        // `mov foo@GOTPCREL(%rip), %reg` become `lea foo(%rip), %reg` when
+        // the address is known at link time, other ones use the GOT entry
+        let position_independent = opt.shared || opt.pie;
+        let link_time_address = |target: &RelocationTarget| {
+            let section_name = match target {
+                RelocationTarget::Section((name, _)) => name,
+                RelocationTarget::Symbol(name) => match symbols.get(name) {
+                    Some(symbol) if symbol.is_ifunc => return false,
+                    // only symbols binding locally are known at link time
+                    Some(_)
+                        if dynamic_symbol_index(
+                            opt,
+                            symbols,
+                            plt_dynamic_symbols,
+                            dynamic_symbols,
+                            name,
+                        )
+                        .is_some() =>
+                    {
+                        return false
+                    }
+                    Some(symbol) => &symbol.section_name,
+                    None => return false,
+                },
+            };
+            // lea computes the address from pc, which would move absolute
+            // symbols along with the load address
+            !(position_independent && section_name == ABSOLUTE_SECTION)
+        };
+        for output_section in output_sections.values_mut() {
+            let OutputSection {
+                content,
+                relocations,
+                ..
+            } = output_section;
+            for relocation in relocations.iter_mut() {
+                if !matches!(
+                    relocation.r_type,
+                    R_X86_64_GOTPCRELX | R_X86_64_REX_GOTPCRELX
+                ) {
+                    continue;
+                }
+                // opcode of mov r64, r/m64 before the ModRM byte
+                let opcode = (relocation.offset as usize).checked_sub(2);
+                if opcode.is_some_and(|opcode| content[opcode] == 0x8b)
+                    && link_time_address(&relocation.target)
+                {
+                    info!("Relaxing mov at {:#x} to lea", relocation.offset);
+                    content[opcode.unwrap()] = 0x8d;
+                    relocation.kind = object::RelocationKind::Relative;
+                    relocation.r_type = R_X86_64_PC32;
+                } else {
+                    relocation.kind = object::RelocationKind::GotRelative;
+                    relocation.r_type = R_X86_64_GOTPCREL;
+                }
+                relocation.encoding = object::RelocationEncoding::Generic;
+                relocation.size = 32;
+            }
+        }
+
+        // addresses loaded from GOT entries, filled at link time unless the
+        // symbol is bound by the dynamic linker
+        let targets: BTreeSet<RelocationTarget> = output_sections
+            .values()
+            .flat_map(|output_section| output_section.relocations.iter())
+            .filter(|relocation| {
+                matches!(
+                    relocation.kind,
+                    object::RelocationKind::Got | object::RelocationKind::GotRelative
+                )
+            })
+            .map(|relocation| relocation.target.clone())
+            .collect();
+        if !targets.is_empty() {
+            let got = output_sections
+                .entry(".got".to_string())
+                .or_insert_with(|| OutputSection {
+                    name: ".got".to_string(),
+                    is_writable: true,
+                    ..OutputSection::default()
+                });
+            for target in targets {
+                info!("Allocating GOT entry for {:?}", target);
+                let got_offset = got.content.len() as u64;
+                got.content.extend([0; 8]);
+                let r_sym = match &target {
+                    RelocationTarget::Symbol(name) => dynamic_symbol_index(
+                        opt,
+                        symbols,
+                        plt_dynamic_symbols,
+                        dynamic_symbols,
+                        name,
+                    ),
+                    RelocationTarget::Section(_) => None,
+                };
+                match r_sym {
+                    Some(r_sym) => output_relocations
+                        .entry(".rela.dyn".to_string())
+                        .or_default()
+                        .relocations
+                        .push(DynamicRelocation {
+                            section_name: ".got".to_string(),
+                            rel: Rel {
+                                r_offset: got_offset,
+                                r_sym,
+                                r_type: R_X86_64_GLOB_DAT,
+                                r_addend: 0,
+                            },
+                        }),
+                    None => got.relocations.push(Relocation {
+                        offset: got_offset,
+                        kind: object::RelocationKind::Absolute,
+                        encoding: object::RelocationEncoding::Generic,
+                        size: 64,
+                        addend: 0,
+                        r_type: R_X86_64_64,
+                        target: target.clone(),
+                    }),
+                }
+                got_offsets.insert(target, got_offset);
+            }
+        }
+
+        // _GLOBAL_OFFSET_TABLE_ is defined in .got.plt when linking
+        // dynamically, otherwise create .got to hold it on demand
+        if symbols.contains_key("_GLOBAL_OFFSET_TABLE_") {
+            return Ok(());
+        }
+        let got_base_used = output_sections
+            .values()
+            .flat_map(|output_section| output_section.relocations.iter())
+            .any(|relocation| {
+                matches!(
+                    relocation.r_type,
+                    R_X86_64_GOTOFF64 | R_X86_64_GOTPC32 | R_X86_64_GOTPC64 | R_X86_64_GOT32
+                ) || matches!(&relocation.target, RelocationTarget::Symbol(name) if name == "_GLOBAL_OFFSET_TABLE_")
+            });
+        if got_base_used {
+            output_sections
+                .entry(".got".to_string())
+                .or_insert_with(|| OutputSection {
+                    name: ".got".to_string(),
+                    is_writable: true,
+                    ..OutputSection::default()
+                });
+
+            // add _GLOBAL_OFFSET_TABLE_ symbol
+            symbols.insert(
+                "_GLOBAL_OFFSET_TABLE_".to_string(),
+                Symbol {
+                    section_name: ".got".to_string(),
+                    offset: 0,
+                    symbol_name_string_id: None,
+                    symbol_name_dynamic_string_id: None,
+                    is_global: false,
+                    is_plt: false,
+                    sym_size: 0,
+                    is_hidden: false,
+                    is_ifunc: false,
+                    is_stub: false,
+                },
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Absolute addresses in position independent output are relocated by the
+    /// dynamic linker via R_X86_64_RELATIVE, or via R_X86_64_IRELATIVE calling
+    /// the resolver of indirect functions
+    fn generate_relative(&mut self) -> anyhow::Result<()> {
+        let Linker {
+            opt,
+            output_sections,
+            symbols,
+            output_relocations,
+            plt_dynamic_symbols,
+            dynamic_symbols,
+            ..
+        } = self;
+
+        // dynamic relocations need PT_DYNAMIC
+        let position_independent = (opt.shared || opt.pie) && self.dynamic_section;
+        if !position_independent {
+            return Ok(());
+        }
+        // read-only section => count of text relocations
+        let mut text_relocations: BTreeMap<&str, usize> = BTreeMap::new();
+        // read-only section => count of text relocations against IFUNC
+        let mut ifunc_text_relocations: BTreeMap<&str, usize> = BTreeMap::new();
+        // resolvers may read relocated data, so they run last
+        let mut ifunc_relocations: Vec<DynamicRelocation> = vec![];
+        for (name, output_section) in output_sections.iter() {
+            if name == ".got.plt" {
+                // lazy binding entries are relocated by ld.so itself
+                continue;
+            }
+            for relocation in &output_section.relocations {
+                if let (object::RelocationKind::Absolute, 32, RelocationTarget::Symbol(target)) =
+                    (relocation.kind, relocation.size, &relocation.target)
+                {
+                    // 32-bit absolute addresses cannot be relocated at runtime
+                    if opt.shared
+                        && dynamic_symbol_index(
+                            opt,
+                            symbols,
+                            plt_dynamic_symbols,
+                            dynamic_symbols,
+                            target,
+                        )
+                        .is_some()
+                    {
+                        bail!(
+                            "{} against symbol {} at {}+{:#x} cannot be used in shared libraries, recompile with -fPIC",
+                            if relocation.r_type == R_X86_64_32S {
+                                "R_X86_64_32S"
+                            } else {
+                                "R_X86_64_32"
+                            },
+                            demangle::demangle(opt, target),
+                            name,
+                            relocation.offset
+                        );
+                    }
+                }
+                if let (object::RelocationKind::Absolute, object::RelocationEncoding::Generic, 64) =
+                    (relocation.kind, relocation.encoding, relocation.size)
+                {
+                    let is_ifunc = matches!(&relocation.target,
+                        RelocationTarget::Symbol(target) if symbols.get(target).is_some_and(|symbol| symbol.is_ifunc));
+                    // symbols not binding locally are looked up at runtime
+                    let r_sym = match &relocation.target {
+                        RelocationTarget::Symbol(target) if !is_ifunc => dynamic_symbol_index(
+                            opt,
+                            symbols,
+                            plt_dynamic_symbols,
+                            dynamic_symbols,
+                            target,
+                        ),
+                        _ => None,
+                    };
+                    if !output_section.is_writable {
+                        // patched at runtime although not writable
+                        *text_relocations.entry(name).or_default() += 1;
+                        if is_ifunc {
+                            *ifunc_text_relocations.entry(name).or_default() += 1;
+                        }
+                    }
+                    // addend is filled after relocation: address of the
+                    // resolver for R_X86_64_IRELATIVE
+                    let dynamic_relocation = DynamicRelocation {
+                        section_name: name.clone(),
+                        rel: match r_sym {
+                            Some(r_sym) => Rel {
+                                r_offset: relocation.offset,
+                                r_sym,
+                                r_type: R_X86_64_64,
+                                r_addend: relocation.addend,
+                            },
+                            None => Rel {
+                                r_offset: relocation.offset,
+                                r_sym: 0,
+                                r_type: if is_ifunc {
+                                    R_X86_64_IRELATIVE
+                                } else {
+                                    R_X86_64_RELATIVE
+                                },
+                                r_addend: 0,
+                            },
+                        },
+                    };
+                    if is_ifunc {
+                        ifunc_relocations.push(dynamic_relocation);
+                    } else {
+                        output_relocations
+                            .entry(".rela.dyn".to_string())
+                            .or_default()
+                            .relocations
+                            .push(dynamic_relocation);
+                    }
+                }
+            }
+        }
+        if !ifunc_relocations.is_empty() {
+            output_relocations
+                .entry(".rela.dyn".to_string())
+                .or_default()
+                .relocations
+                .extend(ifunc_relocations);
+        }
+
+        if opt.warn_ifunc_textrel {
+            for (name, count) in &ifunc_text_relocations {
+                let message = format!(
+                    "{} IFUNC relocations in read-only section {}, the resolvers run before it is writable",
+                    count, name
+                );
+                user_warning(opt, &message);
+            }
+        }
+
+        let messages: Vec<String> = text_relocations
+            .iter()
+            .map(|(name, count)| {
+                format!(
+                    "{} dynamic relocations in read-only section {}",
+                    count, name
+                )
+            })
+            .collect();
+        match opt.text_relocs_policy {
+            TextRelPolicy::Error if !messages.is_empty() => bail!("{}", messages.join("\n")),
+            TextRelPolicy::Allow | TextRelPolicy::Error => {}
+            TextRelPolicy::Warn => {
+                for message in messages {
+                    user_warning(opt, &message);
+                }
             }
         }
 
         Ok(())
     }
 
+    /// Merge .rela.plt into .rela.dyn for -z combreloc, R_X86_64_RELATIVE
+    /// entries go first and R_X86_64_JUMP_SLOT entries last
+    fn combine_relocations(&mut self) {
+        let Linker {
+            output_relocations,
+            combined_plt_relocations,
+            ..
+        } = self;
+
+        let plt_relocations = output_relocations
+            .remove(".rela.plt")
+            .map(|rela_plt| rela_plt.relocations)
+            .unwrap_or_default();
+        let rela_dyn = output_relocations
+            .entry(".rela.dyn".to_string())
+            .or_default();
+        // stable sort keeps the order within each kind
+        rela_dyn
+            .relocations
+            .sort_by_key(|relocation| relocation.rel.r_type != R_X86_64_RELATIVE);
+        *combined_plt_relocations = Some(rela_dyn.relocations.len());
+        rela_dyn.relocations.extend(plt_relocations);
+        if rela_dyn.relocations.is_empty() {
+            output_relocations.remove(".rela.dyn");
+        }
+    }
+
+    /// Move link time values of R_X86_64_RELATIVE and R_X86_64_IRELATIVE into
+    /// addends, they stay in place only with --apply-dynamic-relocs
+    fn apply_relative(&mut self) {
+        let Linker {
+            opt,
+            output_sections,
+            output_relocations,
+            ..
+        } = self;
+
+        let Some(rela_dyn) = output_relocations.get_mut(".rela.dyn") else {
+            return;
+        };
+        for relocation in &mut rela_dyn.relocations {
+            // link time value of TLS descriptors is the argument, in the
+            // second slot
+            let value_offset = match relocation.rel.r_type {
+                R_X86_64_RELATIVE | R_X86_64_IRELATIVE | R_X86_64_TPOFF64 => {
+                    relocation.rel.r_offset
+                }
+                R_X86_64_TLSDESC => relocation.rel.r_offset + 8,
+                _ => continue,
+            };
+            let content = &mut output_sections
+                .get_mut(&relocation.section_name)
+                .unwrap()
+                .content[value_offset as usize..value_offset as usize + 8];
+            // B + A, and base address is zero at link time
+            relocation.rel.r_addend = i64::from_le_bytes(content.try_into().unwrap());
+            if !opt.apply_dynamic_relocs {
+                content.fill(0);
+            }
+        }
+    }
+
     fn reserve(&mut self, arena: &'a mut Arena<u8>) -> anyhow::Result<()> {
         let Linker {
             opt,
+            script,
             output_sections,
+            debug_sections,
             symbols,
             dynamic_symbols,
             plt_dynamic_symbols,
@@ -687,6 +2220,7 @@ impl<'a> Linker<'a> {
             dynsym_section_index,
             phdr_offset,
             phdr_len,
+            address_gaps,
             ..
         } = self;
 
@@ -694,11 +2228,23 @@ impl<'a> Linker<'a> {
         // and generate layout of executable
         // assume executable is loaded at 0x400000 unless pie
         self.load_address = if opt.shared || opt.pie { 0 } else { 0x400000 };
+        // SECTIONS of linker script decides the order of output sections
+        for (name, output_section) in output_sections.iter_mut() {
+            output_section.script_order = script.section_order(name);
+            output_section.overlay = script.overlay(name);
+            output_section.load_expression = script.load_address(name).cloned();
+            if let Some(align) = script.align(name) {
+                output_section.align = output_section.align.max(align);
+            }
+        }
         // the first page is reserved for ELF header & program header
         writer.reserve_file_header();
-        // for simplicity, use one segment to map them all
-        let mut program_headers_count = 2; // PT_PHDR + PT_LOAD
-        if opt.shared || self.dynamic_link {
+        // for simplicity, use one segment to map them all, unless
+        // --rosegment asks for separate read-only, code and data segments
+        let mut program_headers_count = 1 + segment::load_segments_count(output_sections, opt); // PT_PHDR + PT_LOAD
+                                                                                                // PT_LOAD for each section of OVERLAY or with AT(lma)
+        program_headers_count += segment::separately_loaded_sections(output_sections, opt).len();
+        if self.dynamic_section {
             // PT_DYNAMIC
             program_headers_count += 1;
         }
@@ -706,13 +2252,48 @@ impl<'a> Linker<'a> {
             // PT_INTERP
             program_headers_count += 1;
         }
+        if output_sections.values().any(|section| section.is_tls) {
+            // PT_TLS
+            program_headers_count += 1;
+        }
+        if output_sections.contains_key(".note.gnu.property") {
+            // PT_GNU_PROPERTY
+            program_headers_count += 1;
+        }
+        // sections begin at 0x401000, unless SECTIONS of linker script
+        // assigns the location counter; one more PT_LOAD after each address
+        // gap, whose number is known once laid out: unused ones are PT_NULL
+        let phdr_size = std::mem::size_of::<ProgramHeader64<LittleEndian>>();
+        let count_without_gaps = program_headers_count;
+        let placement = loop {
+            let placement = location::place_sections(
+                output_sections,
+                script,
+                opt,
+                self.load_address,
+                (writer.reserved_len() + program_headers_count * phdr_size) as u64,
+            )?;
+            if count_without_gaps + placement.gaps.len() <= program_headers_count {
+                break placement;
+            }
+            program_headers_count = count_without_gaps + placement.gaps.len();
+        };
         *phdr_offset = writer.reserved_len();
-        *phdr_len = program_headers_count * std::mem::size_of::<ProgramHeader64<LittleEndian>>();
+        *phdr_len = program_headers_count * phdr_size;
         writer.reserve_program_headers(program_headers_count as u32);
 
-        // thus sections begin at 0x401000
-        for (_name, output_section) in output_sections.iter_mut() {
-            output_section.offset = writer.reserve(output_section.content.len(), 4096) as u64;
+        self.load_address = placement.load_address;
+        for name in segment::section_order(output_sections, opt) {
+            let output_section = output_sections.get_mut(&name).unwrap();
+            writer.reserve_until(placement.offsets[&name] as usize);
+            output_section.offset = writer.reserve(output_section.content.len(), 1) as u64;
+            output_section.after_location_gap = placement.after_gap.contains(&name);
+        }
+        *address_gaps = placement.gaps;
+        if let Some(end) = segment::sections_end(output_sections, opt) {
+            if opt.separate_code {
+                writer.reserve_until(end as usize);
+            }
         }
         info!("Got {} output sections", output_sections.len());
 
@@ -738,10 +2319,15 @@ impl<'a> Linker<'a> {
                 Some(writer.add_section_name(arena.alloc_str(name).as_bytes()));
             writer.reserve_section_index();
         }
+        for (name, output_section) in debug_sections.iter_mut() {
+            output_section.name_string_id =
+                Some(writer.add_section_name(arena.alloc_str(name).as_bytes()));
+            output_section.section_index = Some(writer.reserve_section_index());
+        }
         let _symtab_section_index = writer.reserve_symtab_section_index();
         let _strtab_section_index = writer.reserve_strtab_section_index();
         let _shstrtab_section_index = writer.reserve_shstrtab_section_index();
-        if opt.shared || self.dynamic_link {
+        if self.dynamic_section {
             // .dynamic, .dynsym, .dynstr, .hash, .gnu_hash
             *dynamic_section_index = writer.reserve_dynamic_section_index();
             *dynsym_section_index = writer.reserve_dynsym_section_index();
@@ -770,7 +2356,7 @@ impl<'a> Linker<'a> {
 
         // reserve dynamic, dynsym, dynstr, hash and gnu_hash
         self.dynamic_entries_count = 6;
-        if opt.shared || self.dynamic_link {
+        if self.dynamic_section {
             // dynamic entries:
             // 1. HASH -> .hash
             // 2. GNU_HASH -> .gnu_hash
@@ -779,13 +2365,19 @@ impl<'a> Linker<'a> {
             // 5. STRSZ
             // 6. SYMENT
             // 7. SONAME
-            // 8. PLTGOT -> .got.plt
-            // 9. PLTRELSZ
-            // 10. PLTREL
-            // 11. JMPREL -> .rela.plt
-            // 12. NEEDED
-            // 13. FLAGS_1
-            // 14. NULL
+            // 8. RUNPATH or RPATH
+            // 9. PLTGOT -> .got.plt
+            // 10. PLTRELSZ
+            // 11. PLTREL
+            // 12. JMPREL -> .rela.plt
+            // 13. RELA -> .rela.dyn
+            // 14. RELASZ
+            // 15. RELAENT
+            // 16. RELACOUNT
+            // 17. NEEDED
+            // 18. FLAGS
+            // 19. FLAGS_1
+            // 20. NULL
             if opt.hash_style.sysv {
                 // HASH
                 self.dynamic_entries_count += 1;
@@ -798,12 +2390,28 @@ impl<'a> Linker<'a> {
                 // SONAME
                 self.dynamic_entries_count += 1;
             }
+            if !opt.rpath.is_empty() {
+                // RUNPATH or RPATH
+                self.dynamic_entries_count += 1;
+            }
             if self.dynamic_link {
                 // PLTGOT, PLTRELSZ, PLTREL, JMPREL
                 self.dynamic_entries_count += 4;
             }
+            if output_relocations.contains_key(".rela.dyn") {
+                // RELA, RELASZ, RELAENT
+                self.dynamic_entries_count += 3;
+                if self.combined_plt_relocations.is_some() {
+                    // RELACOUNT
+                    self.dynamic_entries_count += 1;
+                }
+            }
             // NEEDED
             self.dynamic_entries_count += self.needed.len();
+            if (opt.shared && opt.symbolic) || opt.now {
+                // FLAGS
+                self.dynamic_entries_count += 1;
+            }
 
             // align to 8 bytes boundary
             self.dynamic_section_offset = writer.reserve_dynamic(self.dynamic_entries_count) as u64;
@@ -822,6 +2430,17 @@ impl<'a> Linker<'a> {
                     Some(writer.add_dynamic_string(arena.alloc_str(soname).as_bytes()))
             };
 
+            if opt.origin && !opt.rpath.iter().any(|path| path.contains("$ORIGIN")) {
+                let message = "-z origin without $ORIGIN in -rpath";
+                user_warning(opt, message);
+            }
+            if !opt.rpath.is_empty() {
+                // search paths of all -rpath options, colon separated
+                self.rpath_dynamic_string_index = Some(
+                    writer.add_dynamic_string(arena.alloc_str(&opt.rpath.join(":")).as_bytes()),
+                );
+            }
+
             for needed in &mut self.needed {
                 needed.name_string_id =
                     Some(writer.add_dynamic_string(arena.alloc_str(&needed.name).as_bytes()));
@@ -829,7 +2448,8 @@ impl<'a> Linker<'a> {
 
             self.dynsym_section_offset = writer.reserve_dynsym() as u64;
 
-            // dynamic string
+            // dynamic string, static pie may have no dynamic strings at all
+            writer.require_dynstr();
             self.dynstr_section_offset = writer.reserve_dynstr() as u64;
 
             // hash table
@@ -851,6 +2471,13 @@ impl<'a> Linker<'a> {
             }
         };
 
+        // debug sections are reserved after relocation, unmapped at the end
+        // of file
+        self.loaded_len = writer.reserved_len() as u64;
+
+        // .bss is last in memory and takes no file space
+        segment::reserve_nobits(output_sections, self.loaded_len);
+
         Ok(())
     }
 
@@ -858,27 +2485,62 @@ impl<'a> Linker<'a> {
         let Linker {
             opt,
             output_sections,
+            debug_sections,
             output_relocations,
             symbols,
             dynamic_symbols,
             plt_dynamic_symbols,
             writer,
             soname_dynamic_string_index,
+            rpath_dynamic_string_index,
             section_address,
             ..
         } = self;
+        let address = |offset| segment::address(self.load_address, &self.address_gaps, offset);
 
         // all set! we can now write actual data to buffer
-        // compute entrypoint address
-        let entry_address = if opt.shared {
+        // compute entrypoint address, like GNU ld: -e, then ENTRY of linker
+        // script, then _start, then start of .text, then 0
+        let entry_address = if let Some(entry_address) = opt.entry_address {
+            // explicit address takes precedence over symbol
+            entry_address
+        } else if opt.shared && opt.entry_symbol.is_none() {
             // building shared library, no entrypoint
             0
         } else {
-            let entry_symbol = &symbols["_start"];
-            section_address[&entry_symbol.section_name] + entry_symbol.offset
+            let entry_name = opt.entry_symbol.as_deref().unwrap_or("_start");
+            match symbols.get(entry_name) {
+                Some(entry_symbol) => {
+                    section_address[&entry_symbol.section_name] + entry_symbol.offset
+                }
+                // neither -e nor ENTRY: start of .text, or 0 without .text
+                None if opt.entry_symbol.is_none() => {
+                    let address = section_address.get(".text").copied().unwrap_or(0);
+                    let message = format!(
+                        "Cannot find entry symbol {}, defaulting to {:#x}",
+                        entry_name, address
+                    );
+                    user_warning(opt, &message);
+                    address
+                }
+                // ET_DYN may be loaded without being executed, default to 0
+                // like GNU ld
+                None if opt.shared || opt.pie => {
+                    let message = format!(
+                        "Cannot find entry symbol {}, defaulting to 0",
+                        demangle::demangle(opt, entry_name)
+                    );
+                    user_warning(opt, &message);
+                    0
+                }
+                None => bail!(
+                    "Undefined entry symbol {}",
+                    demangle::demangle(opt, entry_name)
+                ),
+            }
         };
 
-        // ELF header
+        // ELF header, e_version is always EV_CURRENT
         writer.write_file_header(&FileHeader {
             os_abi: 0,
             abi_version: 0,
@@ -941,25 +2603,67 @@ impl<'a> Linker<'a> {
         // initialized area. The file size may not be larger than the memory
         // size. Loadable segment entries in the program header table appear in
         // ascending order, sorted on the p_vaddr member.
-        writer.write_program_header(&ProgramHeader {
-            p_type: object::elf::PT_LOAD,
-            p_flags: object::elf::PF_X | object::elf::PF_W | object::elf::PF_R,
-            p_offset: 0,
-            p_vaddr: self.load_address,
-            p_paddr: self.load_address,
-            p_filesz: writer.reserved_len() as u64,
-            p_memsz: writer.reserved_len() as u64,
-            p_align: 4096,
-        });
-        if opt.shared || self.dynamic_link {
+        let load_segments =
+            segment::load_segments(output_sections, opt, &self.address_gaps, self.loaded_len);
+        for segment in load_segments {
+            // Loadable process segments must have congruent values for
+            // p_vaddr and p_offset, modulo the page size: the file is mapped
+            // page by page
+            let p_align = 4096;
+            let p_vaddr = address(segment.offset);
+            if segment.offset % p_align != p_vaddr % p_align {
+                bail!(
+                    "PT_LOAD at offset {:#x} is not congruent to its address {:#x}",
+                    segment.offset,
+                    p_vaddr
+                );
+            }
+            writer.write_program_header(&ProgramHeader {
+                p_type: object::elf::PT_LOAD,
+                p_flags: segment.flags,
+                p_offset: segment.offset,
+                p_vaddr,
+                p_paddr: p_vaddr,
+                p_filesz: segment.file_size,
+                p_memsz: segment.memory_size,
+                p_align,
+            });
+        }
+        // sections of OVERLAY are mapped again from their load address
+        // (p_offset and p_paddr) to the shared address, the last one wins.
+        // Sections with AT(lma) are mapped again to tell their load address
+        for name in segment::separately_loaded_sections(output_sections, opt) {
+            let output_section = &output_sections[&name];
+            let p_align = 4096;
+            let p_vaddr = section_address[&name];
+            if output_section.offset % p_align != p_vaddr % p_align {
+                bail!(
+                    "PT_LOAD of {} at offset {:#x} is not congruent to its address {:#x}",
+                    name,
+                    output_section.offset,
+                    p_vaddr
+                );
+            }
+            writer.write_program_header(&ProgramHeader {
+                p_type: object::elf::PT_LOAD,
+                p_flags: segment::segment_flags(output_section, true),
+                p_offset: output_section.offset,
+                p_vaddr,
+                p_paddr: output_section.lma,
+                p_filesz: output_section.content.len() as u64,
+                p_memsz: output_section.content.len() as u64,
+                p_align,
+            });
+        }
+        if self.dynamic_section {
             // PT_DYNAMIC The array element specifies dynamic linking
             // information. See ``Dynamic Section'' below for more information.
             writer.write_program_header(&ProgramHeader {
                 p_type: object::elf::PT_DYNAMIC,
                 p_flags: object::elf::PF_W | object::elf::PF_R,
                 p_offset: self.dynamic_section_offset,
-                p_vaddr: self.dynamic_section_offset + self.load_address,
-                p_paddr: self.dynamic_section_offset + self.load_address,
+                p_vaddr: address(self.dynamic_section_offset),
+                p_paddr: address(self.dynamic_section_offset),
                 p_filesz: (self.dynamic_entries_count
                     * std::mem::size_of::<object::elf::Dyn64<object::LittleEndian>>())
                     as u64,
@@ -969,18 +2673,75 @@ impl<'a> Linker<'a> {
                 p_align: 8,
             });
         }
+        let tls_segment = tls::tls_segment(output_sections, section_address);
+        if let Some(tls_segment) = &tls_segment {
+            // PT_TLS The array element specifies the Thread-Local Storage
+            // template. Implementations need not support this program table
+            // entry. See ``Thread-Local Storage'' below for more information.
+            writer.write_program_header(&ProgramHeader {
+                p_type: object::elf::PT_TLS,
+                p_flags: object::elf::PF_R,
+                p_offset: tls_segment.offset,
+                p_vaddr: tls_segment.address,
+                p_paddr: tls_segment.address,
+                p_filesz: tls_segment.size,
+                p_memsz: tls_segment.size,
+                p_align: tls_segment.align,
+            });
+        }
+        if let Some(property) = output_sections.get(".note.gnu.property") {
+            // PT_GNU_PROPERTY locates .note.gnu.property for the kernel and
+            // dynamic linker
+            let address = section_address[".note.gnu.property"];
+            writer.write_program_header(&ProgramHeader {
+                p_type: object::elf::PT_GNU_PROPERTY,
+                p_flags: object::elf::PF_R,
+                p_offset: property.offset,
+                p_vaddr: address,
+                p_paddr: address,
+                p_filesz: property.content.len() as u64,
+                p_memsz: property.content.len() as u64,
+                p_align: property.align,
+            });
+        }
+        // entries reserved for PT_LOAD after address gaps that did not occur
+        while writer.len() < self.phdr_offset + self.phdr_len {
+            writer.write_program_header(&ProgramHeader {
+                p_type: object::elf::PT_NULL,
+                p_flags: 0,
+                p_offset: 0,
+                p_vaddr: 0,
+                p_paddr: 0,
+                p_filesz: 0,
+                p_memsz: 0,
+                p_align: 0,
+            });
+        }
 
         // write section data
-        for (_name, output_section) in output_sections.iter() {
+        let mut fill = 0;
+        for name in segment::section_order(output_sections, opt) {
+            let output_section = &output_sections[&name];
+            if output_section.after_location_gap {
+                // the gap continues the section before
+                let gap = output_section.offset as usize - writer.len();
+                writer.write(&vec![fill; gap]);
+            }
             writer.pad_until(output_section.offset as usize);
             writer.write(&output_section.content);
+            fill = output_section.fill.unwrap_or(0);
+        }
+        if let Some(end) = segment::sections_end(output_sections, opt) {
+            if opt.separate_code {
+                writer.pad_until(end as usize);
+            }
         }
         for (_name, output_section) in output_relocations.iter() {
             writer.pad_until(output_section.offset as usize);
-            for rel in &output_section.relocations {
+            for relocation in &output_section.relocations {
                 // turn offset into absolute
-                let mut rel = rel.clone();
-                rel.r_offset += section_address[".got.plt"];
+                let mut rel = relocation.rel.clone();
+                rel.r_offset += section_address[&relocation.section_name];
                 writer.write_relocation(true, &rel);
             }
         }
@@ -995,11 +2756,16 @@ impl<'a> Linker<'a> {
             if output_section.is_writable {
                 flags |= object::elf::SHF_WRITE;
             }
+            if output_section.is_tls {
+                flags |= object::elf::SHF_TLS;
+            }
 
             writer.write_section_header(&SectionHeader {
                 name: output_section.name_string_id,
                 sh_type: if output_section.is_bss {
                     object::elf::SHT_NOBITS
+                } else if name.starts_with(".note") {
+                    object::elf::SHT_NOTE
                 } else {
                     object::elf::SHT_PROGBITS
                 },
@@ -1025,32 +2791,54 @@ impl<'a> Linker<'a> {
                 sh_offset: output_section.offset,
                 sh_size: (output_section.relocations.len() * entsize) as u64,
                 sh_link: self.dynsym_section_index.0, // associated to .dynsym
-                sh_info: output_sections
-                    .get(".got.plt")
-                    .unwrap()
-                    .section_index
-                    .unwrap()
-                    .0,
+                sh_info: if name == ".rela.plt" {
+                    output_sections
+                        .get(".got.plt")
+                        .unwrap()
+                        .section_index
+                        .unwrap()
+                        .0
+                } else {
+                    // relocations to multiple sections
+                    0
+                },
                 sh_addralign: 8,
                 sh_entsize: entsize as u64,
             });
         }
+        for output_section in debug_sections.values() {
+            let (flags, align) = if output_section.is_compressed {
+                (object::elf::SHF_COMPRESSED as u64, 8)
+            } else {
+                (0, 1)
+            };
+            writer.write_section_header(&SectionHeader {
+                name: output_section.name_string_id,
+                sh_type: object::elf::SHT_PROGBITS,
+                sh_flags: flags,
+                sh_addr: 0,
+                sh_offset: output_section.offset,
+                sh_size: output_section.content.len() as u64,
+                sh_link: 0,
+                sh_info: 0,
+                sh_addralign: align,
+                sh_entsize: 0,
+            });
+        }
         writer.write_symtab_section_header(
             1 + symbols.iter().filter(|(_name, sym)| !sym.is_global).count() as u32,
         ); // +1: one extra null symbol at the beginning
         writer.write_strtab_section_header();
         writer.write_shstrtab_section_header();
-        if opt.shared || self.dynamic_link {
-            writer.write_dynamic_section_header(self.dynamic_section_offset + self.load_address);
-            writer.write_dynsym_section_header(self.dynsym_section_offset + self.load_address, 1); // one local: null symbol
-            writer.write_dynstr_section_header(self.dynstr_section_offset + self.load_address);
+        if self.dynamic_section {
+            writer.write_dynamic_section_header(address(self.dynamic_section_offset));
+            writer.write_dynsym_section_header(address(self.dynsym_section_offset), 1); // one local: null symbol
+            writer.write_dynstr_section_header(address(self.dynstr_section_offset));
             if opt.hash_style.sysv {
-                writer.write_hash_section_header(self.hash_section_offset + self.load_address);
+                writer.write_hash_section_header(address(self.hash_section_offset));
             }
             if opt.hash_style.gnu {
-                writer.write_gnu_hash_section_header(
-                    self.gnu_hash_section_offset + self.load_address,
-                );
+                writer.write_gnu_hash_section_header(address(self.gnu_hash_section_offset));
             }
         }
 
@@ -1061,24 +2849,45 @@ impl<'a> Linker<'a> {
         symbols_vec.sort_by_key(|(_name, sym)| sym.is_global);
         for (_symbol_name, symbol) in symbols_vec {
             let address = section_address[&symbol.section_name] + symbol.offset;
+            let (st_type, address) = tls::symbol_type_and_value(
+                output_sections,
+                &tls_segment,
+                &symbol.section_name,
+                address,
+            );
+            let st_type = if symbol.is_ifunc {
+                STT_GNU_IFUNC
+            } else if symbol.is_stub {
+                STT_FUNC
+            } else {
+                st_type
+            };
             writer.write_symbol(&Sym {
                 name: symbol.symbol_name_string_id,
-                section: if symbol.is_plt {
-                    None // UNDEF
+                section: if symbol.is_plt || symbol.section_name == ABSOLUTE_SECTION {
+                    None // UNDEF or ABS
                 } else if symbol.section_name == ".dynamic" {
                     Some(self.dynamic_section_index)
                 } else {
                     output_sections[&symbol.section_name].section_index
                 },
                 st_info: if symbol.is_global {
-                    (object::elf::STB_GLOBAL) << 4
+                    ((object::elf::STB_GLOBAL) << 4) | st_type
+                } else {
+                    ((object::elf::STB_LOCAL) << 4) | st_type
+                },
+                st_other: if symbol.is_hidden {
+                    object::elf::STV_HIDDEN
+                } else {
+                    0
+                },
+                st_shndx: if symbol.section_name == ABSOLUTE_SECTION && !symbol.is_plt {
+                    object::elf::SHN_ABS
                 } else {
-                    (object::elf::STB_LOCAL) << 4
+                    0
                 },
-                st_other: 0,
-                st_shndx: 0,
                 st_value: if symbol.is_plt { 0 } else { address },
-                st_size: 0,
+                st_size: symbol.sym_size,
             });
         }
 
@@ -1089,7 +2898,7 @@ impl<'a> Linker<'a> {
         writer.write_shstrtab();
 
         // shared library or dynamic linking
-        if opt.shared || self.dynamic_link {
+        if self.dynamic_section {
             // https://refspecs.linuxbase.org/elf/gabi4+/ch5.dynamic.html#dynamic_section
             // .dynamic section entries:
             // 1. HASH -> .hash
@@ -1103,33 +2912,34 @@ impl<'a> Linker<'a> {
             // 9. PLTRELSZ
             // 10. PLTREL
             // 11. JMPREL -> .rela.plt
-            // 12. NEEDED
-            // 13. FLAGS_1
-            // 14. NULL
+            // 12. RELA -> .rela.dyn
+            // 13. RELASZ
+            // 14. RELAENT
+            // 15. RELACOUNT
+            // 16. NEEDED
+            // 17. FLAGS_1
+            // 18. NULL
             writer.write_align_dynamic();
             if opt.hash_style.sysv {
                 // DT_HASH This element holds the address of the symbol hash
                 // table, described in ``Hash Table''. This hash table refers to
                 // the symbol table referenced by the DT_SYMTAB element.
-                writer.write_dynamic(DT_HASH, self.hash_section_offset + self.load_address);
+                writer.write_dynamic(DT_HASH, address(self.hash_section_offset));
             }
             if opt.hash_style.gnu {
-                writer.write_dynamic(
-                    DT_GNU_HASH,
-                    self.gnu_hash_section_offset + self.load_address,
-                );
+                writer.write_dynamic(DT_GNU_HASH, address(self.gnu_hash_section_offset));
             }
 
             // DT_STRTAB This element holds the address of the string table,
             // described in Chapter 4. Symbol names, library names, and other
             // strings reside in this table.
-            writer.write_dynamic(DT_STRTAB, self.dynstr_section_offset + self.load_address);
+            writer.write_dynamic(DT_STRTAB, address(self.dynstr_section_offset));
 
             // DT_SYMTAB This element holds the address of the symbol table,
             // described in the first part of this chapter, with Elf32_Sym
             // entries for the 32-bit class of files and Elf64_Sym entries for
             // the 64-bit class of files.
-            writer.write_dynamic(DT_SYMTAB, self.dynsym_section_offset + self.load_address);
+            writer.write_dynamic(DT_SYMTAB, address(self.dynsym_section_offset));
 
             // DT_STRSZ This element holds the size, in bytes, of the string
             // table.
@@ -1149,6 +2959,19 @@ impl<'a> Linker<'a> {
                 writer.write_dynamic_string(DT_SONAME, *soname_dynamic_string_index);
             }
 
+            if let Some(rpath_dynamic_string_index) = &rpath_dynamic_string_index {
+                // DT_RUNPATH is searched after LD_LIBRARY_PATH, the older
+                // DT_RPATH before it
+                writer.write_dynamic_string(
+                    if opt.disable_new_dtags {
+                        DT_RPATH
+                    } else {
+                        DT_RUNPATH
+                    },
+                    *rpath_dynamic_string_index,
+                );
+            }
+
             if self.dynamic_link {
                 // DT_PLTGOT This element holds an address associated with the
                 // procedure linkage table and/or the global offset table. See
@@ -1159,9 +2982,23 @@ impl<'a> Linker<'a> {
                 // the relocation entries associated with the procedure linkage
                 // table. If an entry of type DT_JMPREL is present, a
                 // DT_PLTRELSZ must accompany it.
+                // with -z combreloc, plt relocations are at the end of .rela.dyn
+                let (plt_relocations_address, plt_relocations_count) =
+                    match self.combined_plt_relocations {
+                        Some(start) => (
+                            section_address[".rela.dyn"]
+                                + (start * std::mem::size_of::<object::elf::Rela64<LittleEndian>>())
+                                    as u64,
+                            output_relocations[".rela.dyn"].relocations.len() - start,
+                        ),
+                        None => (
+                            section_address[".rela.plt"],
+                            output_relocations[".rela.plt"].relocations.len(),
+                        ),
+                    };
                 writer.write_dynamic(
                     DT_PLTRELSZ,
-                    (output_relocations[".rela.plt"].relocations.len()
+                    (plt_relocations_count
                         * std::mem::size_of::<object::elf::Rela64<LittleEndian>>())
                         as u64,
                 );
@@ -1179,7 +3016,43 @@ impl<'a> Linker<'a> {
                 // initialization, if lazy binding is enabled. If this entry is
                 // present, the related entries of types DT_PLTRELSZ and
                 // DT_PLTREL must also be present.
-                writer.write_dynamic(DT_JMPREL, section_address[".rela.plt"]);
+                writer.write_dynamic(DT_JMPREL, plt_relocations_address);
+            }
+            if let Some(rela_dyn) = output_relocations.get(".rela.dyn") {
+                // DT_RELA This element holds the address of a relocation
+                // table, described in Chapter 4. Entries in the table have
+                // explicit addends, such as Elf32_Rela for the 32-bit file
+                // class or Elf64_Rela for the 64-bit file class.
+                writer.write_dynamic(DT_RELA, section_address[".rela.dyn"]);
+
+                // DT_RELASZ This element holds the total size, in bytes, of
+                // the DT_RELA relocation table.
+                writer.write_dynamic(
+                    DT_RELASZ,
+                    (rela_dyn.relocations.len()
+                        * std::mem::size_of::<object::elf::Rela64<LittleEndian>>())
+                        as u64,
+                );
+
+                // DT_RELAENT This element holds the size, in bytes, of the
+                // DT_RELA relocation entry.
+                writer.write_dynamic(
+                    DT_RELAENT,
+                    std::mem::size_of::<object::elf::Rela64<LittleEndian>>() as u64,
+                );
+
+                if self.combined_plt_relocations.is_some() {
+                    // DT_RELACOUNT holds the number of R_X86_64_RELATIVE
+                    // entries at the start of .rela.dyn
+                    writer.write_dynamic(
+                        DT_RELACOUNT,
+                        rela_dyn
+                            .relocations
+                            .iter()
+                            .take_while(|relocation| relocation.rel.r_type == R_X86_64_RELATIVE)
+                            .count() as u64,
+                    );
+                }
             }
             for needed in &self.needed {
                 // DT_NEEDED This element holds the string table offset of a
@@ -1193,9 +3066,50 @@ impl<'a> Linker<'a> {
                 writer.write_dynamic_string(DT_NEEDED, needed.name_string_id.unwrap());
             }
 
+            let mut flags = 0;
+            if opt.shared && opt.symbolic {
+                // DF_SYMBOLIC: ld.so looks up symbols in the library itself
+                // first. References to its own definitions are already
+                // bound at link time, with either -Bsymbolic or
+                // -Bsymbolic-functions
+                flags |= DF_SYMBOLIC;
+            }
+            if opt.now {
+                // DF_BIND_NOW: ld.so resolves PLT entries at startup
+                // instead of at their first call
+                flags |= DF_BIND_NOW;
+            }
+            if flags != 0 {
+                writer.write_dynamic(DT_FLAGS, flags.into());
+            }
+
             // DT_FLAGS_1 If present, this entry's d_val member holds various
             // state flags.
-            writer.write_dynamic(DT_FLAGS_1, if opt.pie { DF_1_PIE.into() } else { 0 });
+            let mut flags_1 = 0;
+            if opt.pie {
+                flags_1 |= DF_1_PIE;
+            }
+            if opt.nodefaultlib {
+                // ld.so skips the default library directories when looking
+                // up dependencies
+                flags_1 |= DF_1_NODEFLIB;
+            }
+            if opt.nodlopen {
+                // dlopen() of the library fails, enforced by ld.so
+                flags_1 |= DF_1_NOOPEN;
+            }
+            if opt.nodelete {
+                // dlclose() keeps the library loaded
+                flags_1 |= DF_1_NODELETE;
+            }
+            if opt.origin {
+                // ld.so substitutes $ORIGIN in search paths
+                flags_1 |= DF_1_ORIGIN;
+            }
+            if opt.now {
+                flags_1 |= DF_1_NOW;
+            }
+            writer.write_dynamic(DT_FLAGS_1, flags_1.into());
 
             // DT_NULL An entry with a DT_NULL tag marks the end of the _DYNAMIC
             // array.
@@ -1206,18 +3120,34 @@ impl<'a> Linker<'a> {
             for dyn_sym in plt_dynamic_symbols.iter().chain(dynamic_symbols.iter()) {
                 let symbol = symbols.get(&dyn_sym.name).unwrap();
                 let address = section_address[&symbol.section_name] + symbol.offset;
+                let (st_type, address) = tls::symbol_type_and_value(
+                    output_sections,
+                    &tls_segment,
+                    &symbol.section_name,
+                    address,
+                );
+                // the dynamic linker calls resolvers of IFUNC symbols
+                let st_type = if symbol.is_ifunc {
+                    STT_GNU_IFUNC
+                } else {
+                    st_type
+                };
                 writer.write_dynamic_symbol(&Sym {
                     name: symbol.symbol_name_dynamic_string_id,
-                    section: if symbol.is_plt {
+                    section: if symbol.is_plt || symbol.section_name == ABSOLUTE_SECTION {
                         None
                     } else {
                         output_sections[&symbol.section_name].section_index
                     },
-                    st_info: (object::elf::STB_GLOBAL) << 4,
+                    st_info: ((object::elf::STB_GLOBAL) << 4) | st_type,
                     st_other: 0,
-                    st_shndx: 0,
+                    st_shndx: if symbol.section_name == ABSOLUTE_SECTION && !symbol.is_plt {
+                        object::elf::SHN_ABS
+                    } else {
+                        0
+                    },
                     st_value: if symbol.is_plt { 0 } else { address },
-                    st_size: 0,
+                    st_size: symbol.sym_size,
                 });
             }
 
@@ -1264,49 +3194,172 @@ impl<'a> Linker<'a> {
             }
         }
 
+        for output_section in debug_sections.values() {
+            writer.pad_until(output_section.offset as usize);
+            writer.write(&output_section.content);
+        }
+
         assert_eq!(writer.reserved_len(), writer.len());
 
         Ok(())
     }
 
-    fn relocate(&mut self) -> anyhow::Result<()> {
+    /// Verify that no two output sections overlap in memory, except
+    /// sections of the same OVERLAY
+    fn check_sections(&self) -> anyhow::Result<()> {
+        let mut ranges: Vec<(u64, u64, &str, Option<usize>)> = self
+            .output_sections
+            .iter()
+            .map(|(name, output_section)| (name, output_section.content.len() as u64))
+            .chain(
+                self.output_relocations
+                    .iter()
+                    .map(|(name, output_section)| {
+                        (
+                            name,
+                            (output_section.relocations.len()
+                                * std::mem::size_of::<object::elf::Rela64<LittleEndian>>())
+                                as u64,
+                        )
+                    }),
+            )
+            .filter(|(_name, size)| *size > 0)
+            .map(|(name, size)| {
+                let address = self.section_address[name];
+                let overlay = self
+                    .output_sections
+                    .get(name)
+                    .and_then(|output_section| output_section.overlay);
+                (address, address + size, name.as_str(), overlay)
+            })
+            .collect();
+        ranges.sort();
+
+        // compare with the section reaching furthest so far
+        let mut furthest: Option<(u64, u64, &str, Option<usize>)> = None;
+        for (start_b, end_b, name_b, overlay_b) in ranges {
+            let Some((start_a, end_a, name_a, overlay_a)) = furthest else {
+                furthest = Some((start_b, end_b, name_b, overlay_b));
+                continue;
+            };
+            if end_b > end_a {
+                furthest = Some((start_b, end_b, name_b, overlay_b));
+            }
+            if overlay_a.is_some() && overlay_a == overlay_b {
+                continue;
+            }
+            if end_a > start_b {
+                bail!(
+                    "Section {} [{:#x}, {:#x}) overlaps section {} [{:#x}, {:#x})",
+                    name_a,
+                    start_a,
+                    end_a,
+                    name_b,
+                    start_b,
+                    end_b
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Compute mapping from section name to virtual address
+    fn assign_addresses(&mut self) -> anyhow::Result<()> {
         let Linker {
             opt,
             output_sections,
+            debug_sections,
             output_relocations,
-            symbols,
             section_address,
             ..
         } = self;
+        let address = |offset| segment::address(self.load_address, &self.address_gaps, offset);
 
-        // compute mapping from section name to virtual address
-        for (name, output_section) in output_sections.iter() {
-            section_address.insert(name.clone(), output_section.offset + self.load_address);
+        for (name, output_section) in output_sections.iter_mut() {
+            section_address.insert(name.clone(), address(output_section.offset));
+            // stored where it sits in the file unless AT(lma) says otherwise
+            output_section.lma = address(output_section.offset);
+        }
+        // sections of one OVERLAY run at the address of the first one
+        let mut overlay_address: BTreeMap<usize, u64> = BTreeMap::new();
+        for name in segment::overlay_sections(output_sections, opt) {
+            let overlay = output_sections[&name].overlay.unwrap();
+            let address = *overlay_address
+                .entry(overlay)
+                .or_insert(section_address[&name]);
+            section_address.insert(name, address);
         }
         for (name, output_section) in output_relocations.iter() {
-            section_address.insert(name.clone(), output_section.offset + self.load_address);
+            section_address.insert(name.clone(), address(output_section.offset));
         }
-        if opt.shared || self.dynamic_link {
-            section_address.insert(
-                ".dynamic".to_string(),
-                self.load_address + self.dynamic_section_offset,
-            );
+        if self.dynamic_section {
+            section_address.insert(".dynamic".to_string(), address(self.dynamic_section_offset));
         }
+        section_address.insert(ABSOLUTE_SECTION.to_string(), 0);
+        // not loaded
+        for name in debug_sections.keys() {
+            section_address.insert(name.clone(), 0);
+        }
+
+        // in SECTIONS order, AT() may use LOADADDR() of previous sections
+        let mut load_expressions: Vec<(Option<usize>, String, Expression)> = output_sections
+            .iter()
+            .filter_map(|(name, output_section)| {
+                Some((
+                    output_section.script_order,
+                    name.clone(),
+                    output_section.load_expression.clone()?,
+                ))
+            })
+            .collect();
+        load_expressions.sort_by_key(|(script_order, _, _)| *script_order);
+        for (_, name, expression) in load_expressions {
+            let lma = expression
+                .evaluate(&*self)
+                .context(format!("Evaluating load address of {}", name))?;
+            info!("Section {} is loaded at {:#x}", name, lma);
+            self.output_sections.get_mut(&name).unwrap().lma = lma;
+        }
+        Ok(())
+    }
+
+    fn relocate(&mut self) -> anyhow::Result<()> {
+        let Linker {
+            output_sections,
+            debug_sections,
+            symbols,
+            section_address,
+            ..
+        } = self;
+
+        // address of _GLOBAL_OFFSET_TABLE_ and .got
+        let got_section = section_address.get(".got").map(|address| *address as i64);
+        // start of thread local storage block
+        let tls_segment = tls::tls_segment(output_sections, section_address);
+        let tls_start = tls_segment
+            .as_ref()
+            .map(|tls_segment| tls_segment.address as i64);
+        let got = symbols
+            .get("_GLOBAL_OFFSET_TABLE_")
+            .map(|symbol| (section_address[&symbol.section_name] + symbol.offset) as i64);
 
         // compute relocation
-        for (name, output_section) in output_sections.iter_mut() {
+        for (name, output_section) in output_sections.iter_mut().chain(debug_sections.iter_mut()) {
             let _span = info_span!("section", name = name).entered();
             for (index, relocation) in output_section.relocations.iter().enumerate() {
                 let _span = info_span!("relocation", index = index).entered();
-                let target_address = match &relocation.target {
+                let (target_address, target_size) = match &relocation.target {
                     RelocationTarget::Section((name, offset)) => {
                         info!("Relocation is targeting section {}", name);
-                        section_address[name] + offset
+                        (section_address[name] + offset, 0)
                     }
                     RelocationTarget::Symbol(name) => {
                         info!("Relocation is targeting symbol {}", name);
                         let symbol = &symbols[name];
-                        section_address[&symbol.section_name] + symbol.offset
+                        (
+                            section_address[&symbol.section_name] + symbol.offset,
+                            symbol.sym_size,
+                        )
                     }
                 };
 
@@ -1315,7 +3368,9 @@ impl<'a> Linker<'a> {
                 // addend
                 let a = relocation.addend;
                 // pc
-                let p = self.load_address + output_section.offset + relocation.offset;
+                let p = section_address[name] + relocation.offset;
+                // symbol size
+                let z = target_size as i64;
 
                 match (relocation.kind, relocation.encoding, relocation.size) {
                     // R_X86_64_64
@@ -1325,7 +3380,24 @@ impl<'a> Linker<'a> {
                         let value = s.wrapping_add(a);
                         output_section.content
                             [(relocation.offset) as usize..(relocation.offset + 8) as usize]
-                            .copy_from_slice(&(value as i64).to_le_bytes());
+                            .copy_from_slice(&value.to_le_bytes());
+                    }
+                    // R_X86_64_32
+                    (object::RelocationKind::Absolute, object::RelocationEncoding::Generic, 32) => {
+                        info!("Relocation type is R_X86_64_32");
+                        // S + A, zero extended when loaded
+                        let value = s.wrapping_add(a);
+                        let Ok(value) = u32::try_from(value) else {
+                            bail!(
+                                "R_X86_64_32 at {}+{:#x} overflows: value {:#x} does not fit in 32 bits",
+                                name,
+                                relocation.offset,
+                                value
+                            );
+                        };
+                        output_section.content
+                            [(relocation.offset) as usize..(relocation.offset + 4) as usize]
+                            .copy_from_slice(&value.to_le_bytes());
                     }
                     // R_X86_64_32S
                     (
@@ -1350,20 +3422,378 @@ impl<'a> Linker<'a> {
                         // we don't have PLT now, implement as R_X86_64_PC32
                         // S + A - P
                         let value = s.wrapping_add(a).wrapping_sub_unsigned(p);
+                        let Ok(value) = i32::try_from(value) else {
+                            bail!(
+                                "R_X86_64_PLT32 at {}+{:#x} overflows: offset {:#x} does not fit in 32 bits",
+                                name,
+                                relocation.offset,
+                                value
+                            );
+                        };
 
                         output_section.content
                             [(relocation.offset) as usize..(relocation.offset + 4) as usize]
-                            .copy_from_slice(&(value as i32).to_le_bytes());
+                            .copy_from_slice(&value.to_le_bytes());
                     }
                     // R_X86_64_PC32
                     (object::RelocationKind::Relative, object::RelocationEncoding::Generic, 32) => {
                         info!("Relocation type is R_X86_64_PC32");
                         // S + A - P
                         let value = s.wrapping_add(a).wrapping_sub_unsigned(p);
+                        let Ok(value) = i32::try_from(value) else {
+                            bail!(
+                                "R_X86_64_PC32 at {}+{:#x} overflows: offset {:#x} does not fit in 32 bits",
+                                name,
+                                relocation.offset,
+                                value
+                            );
+                        };
 
                         output_section.content
                             [(relocation.offset) as usize..(relocation.offset + 4) as usize]
-                            .copy_from_slice(&(value as i32).to_le_bytes());
+                            .copy_from_slice(&value.to_le_bytes());
+                    }
+                    // R_X86_64_PC64
+                    (object::RelocationKind::Unknown, _, _)
+                        if relocation.r_type == R_X86_64_PC64 =>
+                    {
+                        info!("Relocation type is R_X86_64_PC64");
+                        // S + A - P, reaching the whole address space
+                        let value = s.wrapping_add(a).wrapping_sub_unsigned(p);
+                        output_section.content
+                            [(relocation.offset) as usize..(relocation.offset + 8) as usize]
+                            .copy_from_slice(&value.to_le_bytes());
+                    }
+                    // R_X86_64_GOT32
+                    (object::RelocationKind::Got, object::RelocationEncoding::Generic, 32) => {
+                        info!("Relocation type is R_X86_64_GOT32");
+                        // G + A: offset of the GOT entry from the GOT
+                        let entry =
+                            got_section.unwrap() + self.got_offsets[&relocation.target] as i64;
+                        let value = entry.wrapping_sub(got.unwrap()).wrapping_add(a);
+                        let Ok(value) = i32::try_from(value) else {
+                            bail!(
+                                "R_X86_64_GOT32 at {}+{:#x} overflows: offset {:#x} does not fit in 32 bits",
+                                name,
+                                relocation.offset,
+                                value
+                            );
+                        };
+                        output_section.content
+                            [(relocation.offset) as usize..(relocation.offset + 4) as usize]
+                            .copy_from_slice(&value.to_le_bytes());
+                    }
+                    // R_X86_64_GOTPCREL
+                    (
+                        object::RelocationKind::GotRelative,
+                        object::RelocationEncoding::Generic,
+                        32,
+                    ) => {
+                        info!("Relocation type is R_X86_64_GOTPCREL");
+                        // G + GOT + A - P
+                        let entry =
+                            got_section.unwrap() + self.got_offsets[&relocation.target] as i64;
+                        let value = entry.wrapping_add(a).wrapping_sub_unsigned(p);
+                        let Ok(value) = i32::try_from(value) else {
+                            bail!(
+                                "R_X86_64_GOTPCREL at {}+{:#x} overflows: offset {:#x} does not fit in 32 bits",
+                                name,
+                                relocation.offset,
+                                value
+                            );
+                        };
+                        output_section.content
+                            [(relocation.offset) as usize..(relocation.offset + 4) as usize]
+                            .copy_from_slice(&value.to_le_bytes());
+                    }
+                    // R_X86_64_GOTOFF64
+                    (object::RelocationKind::Unknown, _, _)
+                        if relocation.r_type == R_X86_64_GOTOFF64 =>
+                    {
+                        info!("Relocation type is R_X86_64_GOTOFF64");
+                        // S + A - GOT
+                        let value = s.wrapping_add(a).wrapping_sub(got.unwrap());
+                        output_section.content
+                            [(relocation.offset) as usize..(relocation.offset + 8) as usize]
+                            .copy_from_slice(&value.to_le_bytes());
+                    }
+                    // R_X86_64_GOTPC32
+                    (object::RelocationKind::Unknown, _, _)
+                        if relocation.r_type == R_X86_64_GOTPC32 =>
+                    {
+                        info!("Relocation type is R_X86_64_GOTPC32");
+                        // GOT + A - P
+                        let value = got.unwrap().wrapping_add(a).wrapping_sub_unsigned(p);
+                        let Ok(value) = i32::try_from(value) else {
+                            bail!(
+                                "R_X86_64_GOTPC32 at {}+{:#x} overflows: offset {:#x} does not fit in 32 bits",
+                                name,
+                                relocation.offset,
+                                value
+                            );
+                        };
+                        output_section.content
+                            [(relocation.offset) as usize..(relocation.offset + 4) as usize]
+                            .copy_from_slice(&value.to_le_bytes());
+                    }
+                    // R_X86_64_GOTPC64
+                    (object::RelocationKind::Unknown, _, _)
+                        if relocation.r_type == R_X86_64_GOTPC64 =>
+                    {
+                        info!("Relocation type is R_X86_64_GOTPC64");
+                        // GOT + A - P
+                        let value = got.unwrap().wrapping_add(a).wrapping_sub_unsigned(p);
+                        output_section.content
+                            [(relocation.offset) as usize..(relocation.offset + 8) as usize]
+                            .copy_from_slice(&value.to_le_bytes());
+                    }
+                    // R_X86_64_SIZE32
+                    (object::RelocationKind::Unknown, _, _)
+                        if relocation.r_type == R_X86_64_SIZE32 =>
+                    {
+                        info!("Relocation type is R_X86_64_SIZE32");
+                        // Z + A
+                        let value = z.wrapping_add(a);
+                        let Ok(value) = i32::try_from(value) else {
+                            bail!(
+                                "R_X86_64_SIZE32 at {}+{:#x} overflows: value {:#x} does not fit in 32 bits",
+                                name,
+                                relocation.offset,
+                                value
+                            );
+                        };
+                        output_section.content
+                            [(relocation.offset) as usize..(relocation.offset + 4) as usize]
+                            .copy_from_slice(&value.to_le_bytes());
+                    }
+                    // R_X86_64_SIZE64
+                    (object::RelocationKind::Unknown, _, _)
+                        if relocation.r_type == R_X86_64_SIZE64 =>
+                    {
+                        info!("Relocation type is R_X86_64_SIZE64");
+                        // Z + A
+                        let value = z.wrapping_add(a);
+                        output_section.content
+                            [(relocation.offset) as usize..(relocation.offset + 8) as usize]
+                            .copy_from_slice(&value.to_le_bytes());
+                    }
+                    // R_X86_64_TLSGD
+                    (object::RelocationKind::Unknown, _, _)
+                        if relocation.r_type == R_X86_64_TLSGD =>
+                    {
+                        info!("Relocation type is R_X86_64_TLSGD");
+                        // GOT entry of (module, offset) pair + A - P
+                        let RelocationTarget::Symbol(name) = &relocation.target else {
+                            unreachable!()
+                        };
+                        let entry = got_section.unwrap() + self.tls_gd_got_offsets[name] as i64;
+                        let value = entry.wrapping_add(a).wrapping_sub_unsigned(p);
+                        let Ok(value) = i32::try_from(value) else {
+                            bail!(
+                                "R_X86_64_TLSGD at {}+{:#x} overflows: offset {:#x} does not fit in 32 bits",
+                                name,
+                                relocation.offset,
+                                value
+                            );
+                        };
+                        output_section.content
+                            [(relocation.offset) as usize..(relocation.offset + 4) as usize]
+                            .copy_from_slice(&value.to_le_bytes());
+                    }
+                    // R_X86_64_TLSLD
+                    (object::RelocationKind::Unknown, _, _)
+                        if relocation.r_type == R_X86_64_TLSLD =>
+                    {
+                        info!("Relocation type is R_X86_64_TLSLD");
+                        // GOT entry of (module, 0) pair + A - P
+                        let entry = got_section.unwrap() + self.tls_ld_got_offset.unwrap() as i64;
+                        let value = entry.wrapping_add(a).wrapping_sub_unsigned(p);
+                        let Ok(value) = i32::try_from(value) else {
+                            bail!(
+                                "R_X86_64_TLSLD at {}+{:#x} overflows: offset {:#x} does not fit in 32 bits",
+                                name,
+                                relocation.offset,
+                                value
+                            );
+                        };
+                        output_section.content
+                            [(relocation.offset) as usize..(relocation.offset + 4) as usize]
+                            .copy_from_slice(&value.to_le_bytes());
+                    }
+                    // R_X86_64_DTPOFF64
+                    (object::RelocationKind::Unknown, _, _)
+                        if relocation.r_type == R_X86_64_DTPOFF64 =>
+                    {
+                        info!("Relocation type is R_X86_64_DTPOFF64");
+                        // offset in tls block of the module: S + A - TLS
+                        let value = s.wrapping_add(a).wrapping_sub(tls_start.unwrap());
+                        output_section.content
+                            [(relocation.offset) as usize..(relocation.offset + 8) as usize]
+                            .copy_from_slice(&value.to_le_bytes());
+                    }
+                    // R_X86_64_DTPOFF32
+                    (object::RelocationKind::Unknown, _, _)
+                        if relocation.r_type == R_X86_64_DTPOFF32 =>
+                    {
+                        info!("Relocation type is R_X86_64_DTPOFF32");
+                        // offset in tls block of the module: S + A - TLS
+                        let value = s.wrapping_add(a).wrapping_sub(tls_start.unwrap());
+                        let Ok(value) = i32::try_from(value) else {
+                            bail!(
+                                "R_X86_64_DTPOFF32 at {}+{:#x} overflows: value {:#x} does not fit in 32 bits",
+                                name,
+                                relocation.offset,
+                                value
+                            );
+                        };
+                        output_section.content
+                            [(relocation.offset) as usize..(relocation.offset + 4) as usize]
+                            .copy_from_slice(&value.to_le_bytes());
+                    }
+                    // R_X86_64_GOTTPOFF
+                    (object::RelocationKind::Unknown, _, _)
+                        if relocation.r_type == R_X86_64_GOTTPOFF =>
+                    {
+                        info!("Relocation type is R_X86_64_GOTTPOFF");
+                        // GOT entry of offset from thread pointer + A - P
+                        let RelocationTarget::Symbol(name) = &relocation.target else {
+                            unreachable!()
+                        };
+                        let entry = got_section.unwrap() + self.tls_ie_got_offsets[name] as i64;
+                        let value = entry.wrapping_add(a).wrapping_sub_unsigned(p);
+                        let Ok(value) = i32::try_from(value) else {
+                            bail!(
+                                "R_X86_64_GOTTPOFF at {}+{:#x} overflows: offset {:#x} does not fit in 32 bits",
+                                name,
+                                relocation.offset,
+                                value
+                            );
+                        };
+                        output_section.content
+                            [(relocation.offset) as usize..(relocation.offset + 4) as usize]
+                            .copy_from_slice(&value.to_le_bytes());
+                    }
+                    // R_X86_64_TPOFF32
+                    (object::RelocationKind::Unknown, _, _)
+                        if relocation.r_type == R_X86_64_TPOFF32 =>
+                    {
+                        info!("Relocation type is R_X86_64_TPOFF32");
+                        if self.opt.shared {
+                            bail!(
+                                "R_X86_64_TPOFF32 at {}+{:#x} cannot be used in shared libraries, recompile with -fPIC",
+                                name,
+                                relocation.offset
+                            );
+                        }
+                        // offset from thread pointer: S + A - end of TLS block
+                        let value = tls_segment
+                            .as_ref()
+                            .unwrap()
+                            .thread_pointer_offset(s.wrapping_add(a));
+                        let Ok(value) = i32::try_from(value) else {
+                            bail!(
+                                "R_X86_64_TPOFF32 at {}+{:#x} overflows: offset {:#x} does not fit in 32 bits",
+                                name,
+                                relocation.offset,
+                                value
+                            );
+                        };
+                        output_section.content
+                            [(relocation.offset) as usize..(relocation.offset + 4) as usize]
+                            .copy_from_slice(&value.to_le_bytes());
+                    }
+                    // R_X86_64_TPOFF64
+                    (object::RelocationKind::Unknown, _, _)
+                        if relocation.r_type == R_X86_64_TPOFF64 =>
+                    {
+                        info!("Relocation type is R_X86_64_TPOFF64");
+                        // offset from thread pointer in executables
+                        let value = tls_segment
+                            .as_ref()
+                            .unwrap()
+                            .thread_pointer_offset(s.wrapping_add(a));
+                        output_section.content
+                            [(relocation.offset) as usize..(relocation.offset + 8) as usize]
+                            .copy_from_slice(&value.to_le_bytes());
+                    }
+                    // R_X86_64_GOTPC32_TLSDESC
+                    (object::RelocationKind::Unknown, _, _)
+                        if relocation.r_type == R_X86_64_GOTPC32_TLSDESC =>
+                    {
+                        info!("Relocation type is R_X86_64_GOTPC32_TLSDESC");
+                        let RelocationTarget::Symbol(symbol_name) = &relocation.target else {
+                            unreachable!()
+                        };
+                        let offset = relocation.offset as usize;
+                        match self.tls_desc_got_offsets.get(symbol_name) {
+                            Some(got_offset) => {
+                                // GOT entry of descriptor + A - P
+                                let entry = got_section.unwrap() + *got_offset as i64;
+                                let value = entry.wrapping_add(a).wrapping_sub_unsigned(p);
+                                let Ok(value) = i32::try_from(value) else {
+                                    bail!(
+                                        "R_X86_64_GOTPC32_TLSDESC at {}+{:#x} overflows: offset {:#x} does not fit in 32 bits",
+                                        name,
+                                        offset,
+                                        value
+                                    );
+                                };
+                                output_section.content[offset..offset + 4]
+                                    .copy_from_slice(&value.to_le_bytes());
+                            }
+                            None => {
+                                // executables know the offset from thread
+                                // pointer: lea x@tlsdesc(%rip), %rax becomes
+                                // mov $x@tpoff, %rax
+                                if offset < 3
+                                    || output_section.content[offset - 3..offset]
+                                        != [0x48, 0x8d, 0x05]
+                                {
+                                    bail!(
+                                        "Unexpected instruction for R_X86_64_GOTPC32_TLSDESC at {}+{:#x}",
+                                        name,
+                                        offset
+                                    );
+                                }
+                                let value = tls_segment.as_ref().unwrap().thread_pointer_offset(s);
+                                let Ok(value) = i32::try_from(value) else {
+                                    bail!(
+                                        "R_X86_64_GOTPC32_TLSDESC at {}+{:#x} overflows: offset {:#x} does not fit in 32 bits",
+                                        name,
+                                        offset,
+                                        value
+                                    );
+                                };
+                                output_section.content[offset - 3..offset]
+                                    .copy_from_slice(&[0x48, 0xc7, 0xc0]);
+                                output_section.content[offset..offset + 4]
+                                    .copy_from_slice(&value.to_le_bytes());
+                            }
+                        }
+                    }
+                    // R_X86_64_TLSDESC_CALL
+                    (object::RelocationKind::Unknown, _, _)
+                        if relocation.r_type == R_X86_64_TLSDESC_CALL =>
+                    {
+                        info!("Relocation type is R_X86_64_TLSDESC_CALL");
+                        let RelocationTarget::Symbol(symbol_name) = &relocation.target else {
+                            unreachable!()
+                        };
+                        if !self.tls_desc_got_offsets.contains_key(symbol_name) {
+                            // call *(%rax) becomes nop, %rax already holds
+                            // the offset
+                            let offset = relocation.offset as usize;
+                            if output_section.content.get(offset..offset + 2) != Some(&[0xff, 0x10])
+                            {
+                                bail!(
+                                    "Unexpected instruction for R_X86_64_TLSDESC_CALL at {}+{:#x}",
+                                    name,
+                                    offset
+                                );
+                            }
+                            output_section.content[offset..offset + 2]
+                                .copy_from_slice(&[0x66, 0x90]);
+                        }
                     }
                     _ => unimplemented!("Unimplemented relocation {:?}", relocation),
                 }
@@ -1376,5 +3806,12 @@ impl<'a> Linker<'a> {
 
 /// Do the actual linking
 pub fn link(opt: &Opt) -> anyhow::Result<()> {
+    if opt.print_output_format {
+        println!("{}", output_format(opt)?);
+        if opt.obj_file.is_empty() {
+            // only the format is queried
+            return Ok(());
+        }
+    }
     Linker::link(opt)
 }
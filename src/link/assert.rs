@@ -0,0 +1,55 @@
+use super::Linker;
+use crate::script::Environment;
+use anyhow::{bail, Context};
+use tracing::info;
+
+/// Layout of the output, for expressions evaluated after addresses are
+/// assigned
+impl<'a> Environment for Linker<'a> {
+    fn symbol_address(&self, name: &str) -> Option<u64> {
+        let symbol = self.symbols.get(name)?;
+        Some(self.section_address.get(&symbol.section_name)? + symbol.offset)
+    }
+
+    fn section_address(&self, name: &str) -> Option<u64> {
+        self.output_sections.get(name)?;
+        self.section_address.get(name).copied()
+    }
+
+    fn section_load_address(&self, name: &str) -> Option<u64> {
+        // sections sit in the file where they are loaded, but sections of
+        // OVERLAY run elsewhere and AT(lma) moves the load address
+        Some(self.output_sections.get(name)?.lma)
+    }
+
+    fn section_size(&self, name: &str) -> Option<u64> {
+        Some(self.output_sections.get(name)?.content.len() as u64)
+    }
+
+    fn headers_size(&self) -> Option<u64> {
+        // program headers are reserved right after the ELF header
+        Some((self.phdr_offset + self.phdr_len) as u64)
+    }
+
+    fn location_counter(&self) -> Option<u64> {
+        // only known while sections are placed
+        None
+    }
+}
+
+impl<'a> Linker<'a> {
+    /// Evaluate ASSERT of linker script, fail with its message if false
+    pub(super) fn check_assertions(&self) -> anyhow::Result<()> {
+        for assertion in &self.script.assertions {
+            let value = assertion
+                .condition
+                .evaluate(self)
+                .context(format!("Evaluating assertion {:?}", assertion.message))?;
+            if value == 0 {
+                bail!("{}", assertion.message);
+            }
+            info!("Assertion {:?} holds", assertion.message);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,241 @@
+use super::Linker;
+use crate::opt::CompressDebugSections;
+use anyhow::{anyhow, bail};
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use object::read::elf::{ElfFile64, ElfSection64};
+use object::{Object, ObjectSection, ObjectSymbol};
+use std::collections::BTreeMap;
+use std::io::Write;
+use tracing::{info, warn};
+
+/// An address range from .debug_aranges, relative to an output section
+#[derive(Debug)]
+pub struct DebugArange {
+    pub section_name: String,
+    // offset in output section
+    pub offset: u64,
+    pub length: u64,
+}
+
+fn read_u8(data: &[u8], offset: usize) -> anyhow::Result<u8> {
+    data.get(offset)
+        .copied()
+        .ok_or_else(|| anyhow!("Truncated .debug_aranges"))
+}
+
+fn read_u16(data: &[u8], offset: usize) -> anyhow::Result<u16> {
+    data.get(offset..offset + 2)
+        .map(|bytes| u16::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or_else(|| anyhow!("Truncated .debug_aranges"))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> anyhow::Result<u32> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or_else(|| anyhow!("Truncated .debug_aranges"))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> anyhow::Result<u64> {
+    data.get(offset..offset + 8)
+        .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or_else(|| anyhow!("Truncated .debug_aranges"))
+}
+
+/// End of the set of address ranges beginning at `set_offset`
+fn set_end(data: &[u8], set_offset: usize) -> anyhow::Result<usize> {
+    let end = match read_u32(data, set_offset)? {
+        // 64-bit DWARF, the length follows
+        0xffffffff => (set_offset + 12).saturating_add(read_u64(data, set_offset + 4)? as usize),
+        unit_length if unit_length >= 0xfffffff0 => {
+            bail!("Reserved .debug_aranges unit length {:#x}", unit_length)
+        }
+        unit_length => (set_offset + 4).saturating_add(unit_length as usize),
+    };
+    Ok(end)
+}
+
+/// Parse one set of address ranges, `relocations` resolves the addresses
+fn parse_set(
+    data: &[u8],
+    set_offset: usize,
+    set_end: usize,
+    relocations: &BTreeMap<u64, (String, i64)>,
+) -> anyhow::Result<Vec<DebugArange>> {
+    if read_u32(data, set_offset)? == 0xffffffff {
+        bail!("64-bit DWARF is unsupported");
+    }
+    let version = read_u16(data, set_offset + 4)?;
+    if version != 2 {
+        bail!("version {} is unsupported", version);
+    }
+    let address_size = read_u8(data, set_offset + 10)?;
+    let segment_selector_size = read_u8(data, set_offset + 11)?;
+    if address_size != 8 || segment_selector_size != 0 {
+        bail!(
+            "address size {} and segment selector size {} are unsupported",
+            address_size,
+            segment_selector_size
+        );
+    }
+
+    // tuples are aligned to twice the address size
+    let mut aranges = vec![];
+    let mut offset = set_offset + 16;
+    while offset + 16 <= set_end {
+        let address = read_u64(data, offset)?;
+        let length = read_u64(data, offset + 8)?;
+        if address == 0 && length == 0 {
+            // terminating tuple
+            break;
+        }
+        match relocations.get(&(offset as u64)) {
+            Some((section_name, addend)) => aranges.push(DebugArange {
+                section_name: section_name.clone(),
+                offset: (addend + address as i64) as u64,
+                length,
+            }),
+            None => warn!("Skipping .debug_aranges entry without relocation"),
+        }
+        offset += 16;
+    }
+    Ok(aranges)
+}
+
+/// Parse .debug_aranges of an object file, the addresses are resolved via
+/// relocations against the sections they describe, `placements` maps input
+/// section index to output section and offset. Sets that cannot be parsed
+/// are skipped with a warning
+pub fn parse_debug_aranges(
+    obj: &ElfFile64,
+    section: &ElfSection64,
+    placements: &BTreeMap<usize, (String, u64)>,
+) -> anyhow::Result<Vec<DebugArange>> {
+    let data = section.data()?;
+
+    // section offset => (output section name, offset in output section + addend)
+    let mut relocations = BTreeMap::new();
+    for (offset, relocation) in section.relocations() {
+        if let object::RelocationTarget::Symbol(symbol_id) = relocation.target() {
+            let symbol = obj.symbol_by_index(symbol_id)?;
+            if let Some((section_name, start)) = symbol
+                .section_index()
+                .and_then(|section_index| placements.get(&section_index.0))
+            {
+                relocations.insert(
+                    offset,
+                    (
+                        section_name.clone(),
+                        *start as i64 + symbol.address() as i64 + relocation.addend(),
+                    ),
+                );
+            }
+        }
+    }
+
+    // DWARF 5 Section 6.1.2 Lookup by Address
+    let mut aranges = vec![];
+    let mut set_offset = 0;
+    while set_offset < data.len() {
+        let set_end = match set_end(data, set_offset) {
+            Ok(set_end) => set_end,
+            Err(err) => {
+                // where the next set begins is unknown
+                warn!(
+                    "Skipping rest of .debug_aranges at {:#x}: {}",
+                    set_offset, err
+                );
+                break;
+            }
+        };
+        match parse_set(data, set_offset, set_end, &relocations) {
+            Ok(set) => aranges.extend(set),
+            Err(err) => warn!("Skipping .debug_aranges set at {:#x}: {}", set_offset, err),
+        }
+        set_offset = set_end;
+    }
+    Ok(aranges)
+}
+
+impl<'a> Linker<'a> {
+    /// Check that .debug_aranges covers the whole .text section
+    pub(super) fn check_debug_aranges(&self) {
+        if self.debug_aranges.is_empty() {
+            return;
+        }
+        let Some(text) = self.output_sections.get(".text") else {
+            return;
+        };
+        let text_address = self.section_address[".text"];
+        let text_end = text_address + text.content.len() as u64;
+
+        let mut ranges: Vec<(u64, u64)> = self
+            .debug_aranges
+            .iter()
+            .filter(|arange| arange.section_name == ".text")
+            .map(|arange| {
+                let start = text_address + arange.offset;
+                (start, start + arange.length)
+            })
+            .collect();
+        ranges.sort();
+
+        let mut gaps = vec![];
+        let mut covered = text_address;
+        for (start, end) in ranges {
+            if start > covered {
+                gaps.push((covered, start));
+            }
+            covered = covered.max(end);
+        }
+        if covered < text_end {
+            gaps.push((covered, text_end));
+        }
+
+        if gaps.is_empty() {
+            info!(".debug_aranges covers .text");
+        }
+        for (start, end) in gaps {
+            warn!(
+                "Address range {:#x}..{:#x} in .text is not covered by .debug_aranges",
+                start, end
+            );
+        }
+    }
+
+    /// Compress debug sections if requested, and reserve them at the end of
+    /// file: their size is only known after relocation
+    pub(super) fn reserve_debug_sections(&mut self) -> anyhow::Result<()> {
+        for (name, output_section) in self.debug_sections.iter_mut() {
+            if self.opt.compress_debug_sections == CompressDebugSections::Zlib
+                && !output_section.content.is_empty()
+            {
+                // Elf64_Chdr: ch_type, ch_reserved, ch_size, ch_addralign
+                let mut compressed = vec![];
+                compressed.extend_from_slice(&object::elf::ELFCOMPRESS_ZLIB.to_le_bytes());
+                compressed.extend_from_slice(&0u32.to_le_bytes());
+                compressed.extend_from_slice(&(output_section.content.len() as u64).to_le_bytes());
+                compressed.extend_from_slice(&output_section.align.max(1).to_le_bytes());
+                let mut encoder = ZlibEncoder::new(compressed, Compression::default());
+                encoder.write_all(&output_section.content)?;
+                let compressed = encoder.finish()?;
+
+                // keep the section as is unless it gets smaller, like GNU ld
+                if compressed.len() < output_section.content.len() {
+                    info!(
+                        "Compressed {} from {} to {} bytes",
+                        name,
+                        output_section.content.len(),
+                        compressed.len()
+                    );
+                    output_section.content = compressed;
+                    output_section.is_compressed = true;
+                }
+            }
+
+            let align = if output_section.is_compressed { 8 } else { 1 };
+            output_section.offset = self.writer.reserve(output_section.content.len(), align) as u64;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,14 @@
+use crate::opt::Opt;
+use cpp_demangle::Symbol;
+
+/// Symbol name as shown in diagnostics and map file: C++ names are
+/// demangled unless --no-demangle, other names are kept as is
+pub fn demangle(opt: &Opt, name: &str) -> String {
+    if opt.no_demangle || !name.starts_with("_Z") {
+        return name.to_string();
+    }
+    Symbol::new(name)
+        .ok()
+        .and_then(|symbol| symbol.demangle().ok())
+        .unwrap_or(name.to_string())
+}
@@ -0,0 +1,111 @@
+use super::{OutputSection, Relocation, RelocationTarget};
+use anyhow::{anyhow, bail};
+use std::collections::BTreeMap;
+use tracing::info;
+
+/// CIE content and its relocations (offset in CIE, type, addend, target)
+pub type CieKey = (Vec<u8>, Vec<(u64, u32, i64, RelocationTarget)>);
+
+fn read_u32(data: &[u8], offset: usize) -> anyhow::Result<u32> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or_else(|| anyhow!("Truncated .eh_frame"))
+}
+
+/// Share identical CIEs in .eh_frame: the records of one object starting at
+/// content_start (and its relocations starting at relocations_start) are
+/// rewritten, CIEs seen before in `cies` are dropped and FDEs are pointed to
+/// the canonical CIE
+pub fn dedup_cies(
+    out: &mut OutputSection,
+    content_start: usize,
+    relocations_start: usize,
+    cies: &mut BTreeMap<CieKey, u64>,
+) -> anyhow::Result<()> {
+    let data = out.content.split_off(content_start);
+    let relocations = out.relocations.split_off(relocations_start);
+
+    // input offset => relocation
+    let mut relocations: BTreeMap<u64, Relocation> = relocations
+        .into_iter()
+        .map(|relocation| (relocation.offset - content_start as u64, relocation))
+        .collect();
+
+    // input CIE offset => output CIE offset
+    let mut cie_offsets = BTreeMap::new();
+    let mut offset = 0;
+    while offset < data.len() {
+        // Linux Standard Base Core Specification 10.6.1 The .eh_frame section
+        let length = read_u32(&data, offset)? as usize;
+        if length == 0xffffffff {
+            bail!("64-bit .eh_frame records are unsupported");
+        }
+        let record_len = 4 + length;
+        let record = data
+            .get(offset..offset + record_len)
+            .ok_or_else(|| anyhow!("Truncated .eh_frame"))?;
+        let record_relocations: Vec<u64> = relocations
+            .range(offset as u64..(offset + record_len) as u64)
+            .map(|(relocation_offset, _)| *relocation_offset)
+            .collect();
+
+        let output_offset = out.content.len() as u64;
+        let mut keep = true;
+        if length == 0 {
+            // terminator
+        } else if read_u32(&data, offset + 4)? == 0 {
+            // CIE
+            let key: CieKey = (
+                record.to_vec(),
+                record_relocations
+                    .iter()
+                    .map(|relocation_offset| {
+                        let relocation = &relocations[relocation_offset];
+                        (
+                            relocation_offset - offset as u64,
+                            relocation.r_type,
+                            relocation.addend,
+                            relocation.target.clone(),
+                        )
+                    })
+                    .collect(),
+            );
+            match cies.get(&key) {
+                Some(existing) => {
+                    info!("Sharing CIE at offset {:#x} of .eh_frame", existing);
+                    cie_offsets.insert(offset as u64, *existing);
+                    keep = false;
+                }
+                None => {
+                    cie_offsets.insert(offset as u64, output_offset);
+                    cies.insert(key, output_offset);
+                }
+            }
+        }
+
+        if keep {
+            out.content.extend(record);
+            if length != 0 && read_u32(&data, offset + 4)? != 0 {
+                // FDE: CIE pointer is relative to the pointer itself
+                let cie_offset = (offset as u64 + 4)
+                    .checked_sub(read_u32(&data, offset + 4)? as u64)
+                    .ok_or_else(|| anyhow!("FDE references unknown CIE in .eh_frame"))?;
+                let cie_output_offset = cie_offsets
+                    .get(&cie_offset)
+                    .ok_or_else(|| anyhow!("FDE references unknown CIE in .eh_frame"))?;
+                let cie_pointer = (output_offset + 4 - cie_output_offset) as u32;
+                out.content[output_offset as usize + 4..output_offset as usize + 8]
+                    .copy_from_slice(&cie_pointer.to_le_bytes());
+            }
+        }
+        for relocation_offset in record_relocations {
+            let mut relocation = relocations.remove(&relocation_offset).unwrap();
+            if keep {
+                relocation.offset = output_offset + relocation_offset - offset as u64;
+                out.relocations.push(relocation);
+            }
+        }
+        offset += record_len;
+    }
+    Ok(())
+}
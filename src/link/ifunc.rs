@@ -0,0 +1,137 @@
+use super::{DynamicRelocation, Linker, OutputSection, Relocation, RelocationTarget};
+use anyhow::bail;
+use object::elf::{R_X86_64_64, R_X86_64_IRELATIVE, R_X86_64_PC32};
+use object::write::elf::Rel;
+use std::collections::{BTreeMap, BTreeSet};
+use tracing::info;
+
+impl<'a> Linker<'a> {
+    /// Calls to indirect functions go through .iplt: each entry jumps to a
+    /// .got.iplt slot, which the dynamic linker fills with the address
+    /// returned by the resolver via R_X86_64_IRELATIVE
+    pub(super) fn generate_iplt(&mut self) -> anyhow::Result<()> {
+        let Linker {
+            opt,
+            output_sections,
+            symbols,
+            output_relocations,
+            ..
+        } = self;
+
+        // absolute addresses in position independent output already got
+        // R_X86_64_IRELATIVE in generate_relative
+        let position_independent = (opt.shared || opt.pie) && self.dynamic_section;
+        let through_iplt = |relocation: &Relocation| match &relocation.target {
+            RelocationTarget::Symbol(name) => {
+                symbols.get(name).is_some_and(|symbol| symbol.is_ifunc)
+                    && !(position_independent
+                        && (relocation.kind, relocation.encoding, relocation.size)
+                            == (
+                                object::RelocationKind::Absolute,
+                                object::RelocationEncoding::Generic,
+                                64,
+                            ))
+            }
+            RelocationTarget::Section(_) => false,
+        };
+        let names: BTreeSet<String> = output_sections
+            .values()
+            .flat_map(|output_section| output_section.relocations.iter())
+            .filter(|relocation| through_iplt(relocation))
+            .filter_map(|relocation| match &relocation.target {
+                RelocationTarget::Symbol(name) => Some(name.clone()),
+                RelocationTarget::Section(_) => None,
+            })
+            .collect();
+        if names.is_empty() {
+            return Ok(());
+        }
+        if !self.dynamic_section {
+            // static executables need the startup code to apply
+            // .rela.iplt, which is not emitted
+            bail!(
+                "IFUNC symbols are unsupported in static executables: {}",
+                names.into_iter().collect::<Vec<_>>().join(", ")
+            );
+        }
+
+        assert!(!output_sections.contains_key(".iplt"));
+        let mut iplt = OutputSection {
+            name: ".iplt".to_string(),
+            is_executable: true,
+            ..OutputSection::default()
+        };
+        assert!(!output_sections.contains_key(".got.iplt"));
+        let mut got_iplt = OutputSection {
+            name: ".got.iplt".to_string(),
+            // written by ld.so when calling the resolvers
+            is_writable: true,
+            ..OutputSection::default()
+        };
+        let rela_dyn = output_relocations
+            .entry(".rela.dyn".to_string())
+            .or_default();
+        // IFUNC symbol => offset of its entry in .iplt
+        let mut entries: BTreeMap<String, u64> = BTreeMap::new();
+        for name in names {
+            let plt_offset = iplt.content.len() as u64;
+            info!("Calling IFUNC symbol {} through .iplt", name);
+            let got_offset = got_iplt.content.len() as u64;
+
+            // each entry in iplt:
+            // ff 25 xx xx xx xx jmp *.got.iplt+yy(%rip)
+            // 66 90             nop
+            iplt.content
+                .extend(vec![0xff, 0x25, 0x00, 0x00, 0x00, 0x00, 0x66, 0x90]);
+            iplt.relocations.push(Relocation {
+                offset: 0x2 + plt_offset,
+                kind: object::RelocationKind::Relative,
+                encoding: object::RelocationEncoding::Generic,
+                size: 32,
+                addend: got_offset as i64 - 4,
+                r_type: R_X86_64_PC32,
+                target: RelocationTarget::Section((".got.iplt".to_string(), 0)),
+            });
+
+            // link time value is the address of the resolver, moved into
+            // the addend of R_X86_64_IRELATIVE
+            got_iplt.content.extend(vec![0; 8]);
+            got_iplt.relocations.push(Relocation {
+                offset: got_offset,
+                kind: object::RelocationKind::Absolute,
+                encoding: object::RelocationEncoding::Generic,
+                size: 64,
+                addend: 0,
+                r_type: R_X86_64_64,
+                target: RelocationTarget::Symbol(name.clone()),
+            });
+            rela_dyn.relocations.push(DynamicRelocation {
+                section_name: ".got.iplt".to_string(),
+                rel: Rel {
+                    r_offset: got_offset,
+                    r_sym: 0,
+                    r_type: R_X86_64_IRELATIVE,
+                    r_addend: 0,
+                },
+            });
+            entries.insert(name, plt_offset);
+        }
+
+        // calls and addresses in executables point to the entries, the
+        // symbol itself stays the resolver
+        for output_section in output_sections.values_mut() {
+            for relocation in &mut output_section.relocations {
+                if !through_iplt(relocation) {
+                    continue;
+                }
+                if let RelocationTarget::Symbol(name) = &relocation.target {
+                    relocation.target =
+                        RelocationTarget::Section((".iplt".to_string(), entries[name]));
+                }
+            }
+        }
+        output_sections.insert(".iplt".to_string(), iplt);
+        output_sections.insert(".got.iplt".to_string(), got_iplt);
+        Ok(())
+    }
+}
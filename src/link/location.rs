@@ -0,0 +1,140 @@
+use super::segment::{self, AddressGap};
+use super::OutputSection;
+use crate::opt::Opt;
+use crate::script::{Environment, Script};
+use anyhow::{bail, Context};
+use std::collections::{BTreeMap, BTreeSet};
+use tracing::info;
+
+/// Layout in progress, for assignments to the location counter in SECTIONS
+/// of linker script: besides `.` and SIZEOF_HEADERS, only sections placed
+/// before are known
+pub struct LocationCounter<'a> {
+    pub address: u64,
+    pub headers_size: u64,
+    // section name => (address, size)
+    pub placed: &'a BTreeMap<String, (u64, u64)>,
+}
+
+impl Environment for LocationCounter<'_> {
+    fn symbol_address(&self, _name: &str) -> Option<u64> {
+        None
+    }
+
+    fn section_address(&self, name: &str) -> Option<u64> {
+        self.placed.get(name).map(|(address, _)| *address)
+    }
+
+    fn section_load_address(&self, _name: &str) -> Option<u64> {
+        // AT(lma) is evaluated after layout
+        None
+    }
+
+    fn section_size(&self, name: &str) -> Option<u64> {
+        self.placed.get(name).map(|(_, size)| *size)
+    }
+
+    fn headers_size(&self) -> Option<u64> {
+        Some(self.headers_size)
+    }
+
+    fn location_counter(&self) -> Option<u64> {
+        Some(self.address)
+    }
+}
+
+/// Where sections backed by file content go, in file order after the
+/// headers. Assigning the location counter in SECTIONS of linker script moves
+/// the section there: a gap within the next page is filled, a longer one is
+/// an address gap and starts a new PT_LOAD
+pub struct Placement {
+    pub load_address: u64,
+    // section name => file offset
+    pub offsets: BTreeMap<String, u64>,
+    // sections after a filled gap
+    pub after_gap: BTreeSet<String>,
+    pub gaps: Vec<AddressGap>,
+}
+
+/// Place sections after `headers_size` bytes of headers: it decides the
+/// number of program headers, so the caller tries again if it differs
+pub fn place_sections(
+    output_sections: &BTreeMap<String, OutputSection>,
+    script: &Script,
+    opt: &Opt,
+    mut load_address: u64,
+    headers_size: u64,
+) -> anyhow::Result<Placement> {
+    let mut offsets = BTreeMap::new();
+    let mut after_gap = BTreeSet::new();
+    let mut gaps = vec![];
+    let mut end = headers_size;
+    let mut location_assignments = script.location_assignments.iter().peekable();
+    // section name => (address, size), of sections placed so far
+    let mut placed: BTreeMap<String, (u64, u64)> = BTreeMap::new();
+    for name in segment::section_order(output_sections, opt) {
+        let output_section = &output_sections[&name];
+        let mut align = output_section.align.max(4096);
+        if let Some(order) = output_section.script_order {
+            let mut current = segment::address(load_address, &gaps, end);
+            let mut location = current;
+            while let Some(assignment) =
+                location_assignments.next_if(|assignment| assignment.section <= order)
+            {
+                location = assignment
+                    .expression
+                    .evaluate(&LocationCounter {
+                        address: location,
+                        headers_size,
+                        placed: &placed,
+                    })
+                    .context(format!("Evaluating location counter before {}", name))?;
+                info!("Location counter = {:#x}", location);
+            }
+            if location != current {
+                if placed.is_empty() && !(opt.shared || opt.pie) {
+                    // the headers move along, in the page below
+                    load_address = location.saturating_sub(headers_size) / 4096 * 4096;
+                    current = load_address + headers_size;
+                }
+                if location < current {
+                    bail!(
+                        "Location counter moved backwards from {:#x} to {:#x} before {}",
+                        current,
+                        location,
+                        name
+                    );
+                }
+                if location / 4096 * 4096 > current.next_multiple_of(4096) {
+                    // leaves whole pages unused: start over in a new page, at
+                    // an offset congruent to the address
+                    end = end.next_multiple_of(4096) + location % 4096;
+                    gaps.push(AddressGap {
+                        offset: end,
+                        shift: location - load_address - end,
+                    });
+                } else {
+                    end += location - current;
+                    after_gap.insert(name.clone());
+                }
+                align = output_section.align.max(1);
+            }
+        }
+        let offset = end.next_multiple_of(align);
+        end = offset + output_section.content.len() as u64;
+        offsets.insert(name.clone(), offset);
+        placed.insert(
+            name,
+            (
+                segment::address(load_address, &gaps, offset),
+                output_section.content.len() as u64,
+            ),
+        );
+    }
+    Ok(Placement {
+        load_address,
+        offsets,
+        after_gap,
+        gaps,
+    })
+}
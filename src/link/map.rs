@@ -0,0 +1,70 @@
+use super::demangle::demangle;
+use super::Linker;
+use anyhow::Context;
+use std::fmt::Write;
+
+impl<'a> Linker<'a> {
+    /// Write the link map: output sections, the input sections copied to
+    /// them and the symbols defined in each
+    pub(super) fn write_map(&self, path: &str) -> anyhow::Result<()> {
+        let mut map = String::new();
+        writeln!(map, "Memory map")?;
+        writeln!(map)?;
+        for (name, output_section) in &self.output_sections {
+            let address = self.section_address[name];
+            writeln!(
+                map,
+                "{:<15} {:#018x} {:#10x}",
+                name,
+                address,
+                output_section.content.len()
+            )?;
+
+            // symbols sorted by offset from section start
+            let mut symbols: Vec<(&String, u64)> = self
+                .symbols
+                .iter()
+                .filter(|(_symbol_name, symbol)| &symbol.section_name == name)
+                .map(|(symbol_name, symbol)| (symbol_name, symbol.offset))
+                .collect();
+            symbols.sort_by_key(|(symbol_name, offset)| (*offset, *symbol_name));
+            let write_symbol = |map: &mut String, symbol_name: &str, offset: u64| {
+                writeln!(
+                    map,
+                    "                {:#018x} {:#10x} {}",
+                    address + offset,
+                    offset,
+                    demangle(&self.opt, symbol_name)
+                )
+            };
+
+            // symbols follow the input section containing them
+            let mut listed = vec![false; symbols.len()];
+            for input in &output_section.inputs {
+                writeln!(
+                    map,
+                    " {:<14} {:#018x} {:#10x} {}",
+                    input.name,
+                    address + input.start,
+                    input.end - input.start,
+                    input.file_name
+                )?;
+                for (index, (symbol_name, offset)) in symbols.iter().enumerate() {
+                    if !listed[index] && (input.start..input.end).contains(offset) {
+                        write_symbol(&mut map, symbol_name, *offset)?;
+                        listed[index] = true;
+                    }
+                }
+            }
+            // symbols defined by the linker
+            for (index, (symbol_name, offset)) in symbols.iter().enumerate() {
+                if !listed[index] {
+                    write_symbol(&mut map, symbol_name, *offset)?;
+                }
+            }
+            writeln!(map)?;
+        }
+        std::fs::write(path, map).context(format!("Writing map file {}", path))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,48 @@
+use super::Linker;
+use anyhow::bail;
+use tracing::info;
+
+impl<'a> Linker<'a> {
+    /// Check that output sections assigned to MEMORY regions of linker script
+    /// fit in their LENGTH
+    pub(super) fn check_memory_regions(&self) -> anyhow::Result<()> {
+        let mut overflows = vec![];
+        for region in &self.script.memory {
+            // sections packed one after another in the region
+            let mut used: u64 = 0;
+            let mut names = vec![];
+            for description in &self.script.sections {
+                if description.region.as_ref() != Some(&region.name)
+                    && description.load_region.as_ref() != Some(&region.name)
+                {
+                    continue;
+                }
+                if let Some(output_section) = self.output_sections.get(&description.name) {
+                    used = used.next_multiple_of(output_section.align.max(1))
+                        + output_section.content.len() as u64;
+                    names.push(description.name.as_str());
+                }
+            }
+
+            if used > region.length {
+                overflows.push(format!(
+                    "region {} overflowed by {} bytes ({} of {} bytes used by {})",
+                    region.name,
+                    used - region.length,
+                    used,
+                    region.length,
+                    names.join(", ")
+                ));
+            } else {
+                info!(
+                    "Memory region {} uses {:#x} of {:#x} bytes",
+                    region.name, used, region.length
+                );
+            }
+        }
+        if !overflows.is_empty() {
+            bail!("{}", overflows.join("\n"));
+        }
+        Ok(())
+    }
+}
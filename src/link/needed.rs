@@ -0,0 +1,51 @@
+use super::{Linker, RelocationTarget};
+use std::collections::BTreeSet;
+use tracing::info;
+
+impl<'a> Linker<'a> {
+    /// --as-needed and --no-add-needed: shared libraries providing no
+    /// referenced symbol get no DT_NEEDED, and their symbols are not bound
+    /// through .plt
+    pub(super) fn remove_unused_needed(&mut self) {
+        let add_needed = self.opt.add_needed;
+        if add_needed && !self.needed.iter().any(|needed| needed.as_needed) {
+            return;
+        }
+        let referenced: BTreeSet<&String> = self
+            .output_sections
+            .values()
+            .flat_map(|output_section| output_section.relocations.iter())
+            .filter_map(|relocation| match &relocation.target {
+                RelocationTarget::Symbol(name) => Some(name),
+                RelocationTarget::Section(_) => None,
+            })
+            .collect();
+        let (used, unused): (Vec<_>, Vec<_>) = std::mem::take(&mut self.needed)
+            .into_iter()
+            .partition(|needed| {
+                add_needed && !needed.as_needed
+                    || needed.symbols.iter().any(|name| referenced.contains(name))
+            });
+        if unused.is_empty() {
+            self.needed = used;
+            return;
+        }
+
+        // symbols also defined by a used library stay
+        let kept: BTreeSet<&String> = used.iter().flat_map(|needed| &needed.symbols).collect();
+        let removed: BTreeSet<&String> = unused
+            .iter()
+            .flat_map(|needed| &needed.symbols)
+            .filter(|name| !kept.contains(name))
+            .collect();
+        for needed in &unused {
+            info!(
+                "Dropping DT_NEEDED {}, no symbol is referenced",
+                needed.name
+            );
+        }
+        self.plt_dynamic_symbols
+            .retain(|dyn_sym| !removed.contains(&dyn_sym.name));
+        self.needed = used;
+    }
+}
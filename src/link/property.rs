@@ -0,0 +1,49 @@
+use super::OutputSection;
+use object::elf::{ELF_NOTE_GNU, NT_GNU_PROPERTY_TYPE_0};
+use object::read::elf::{ElfSection64, SectionHeader};
+
+// x86 processor features, not defined in object crate yet
+pub const GNU_PROPERTY_X86_FEATURE_1_AND: u32 = 0xc0000002;
+
+/// Find GNU_PROPERTY_X86_FEATURE_1_AND in .note.gnu.property of an object
+pub fn x86_feature_1_and(section: &ElfSection64) -> anyhow::Result<Option<u32>> {
+    let endian = section.elf_file().endian();
+    let data = section.elf_file().data();
+    let Some(mut notes) = section.elf_section_header().notes(endian, data)? else {
+        return Ok(None);
+    };
+    while let Some(note) = notes.next()? {
+        let Some(mut properties) = note.gnu_properties(endian) else {
+            continue;
+        };
+        while let Some(property) = properties.next()? {
+            if property.pr_type() == GNU_PROPERTY_X86_FEATURE_1_AND {
+                return Ok(Some(property.data_u32(endian)?));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Build .note.gnu.property with the combined x86 features
+pub fn gnu_property_section(x86_feature_1_and: u32) -> OutputSection {
+    let mut content = vec![];
+    // n_namesz, n_descsz, n_type
+    content.extend(((ELF_NOTE_GNU.len() + 1) as u32).to_le_bytes());
+    content.extend(16u32.to_le_bytes());
+    content.extend(NT_GNU_PROPERTY_TYPE_0.to_le_bytes());
+    content.extend(ELF_NOTE_GNU);
+    content.push(0);
+    // pr_type, pr_datasz, pr_data, padded to 8 bytes
+    content.extend(GNU_PROPERTY_X86_FEATURE_1_AND.to_le_bytes());
+    content.extend(4u32.to_le_bytes());
+    content.extend(x86_feature_1_and.to_le_bytes());
+    content.extend(0u32.to_le_bytes());
+
+    OutputSection {
+        name: ".note.gnu.property".to_string(),
+        content,
+        align: 8,
+        ..OutputSection::default()
+    }
+}
@@ -0,0 +1,155 @@
+use super::demangle::demangle;
+use super::{
+    sort_dynamic_symbols, DynamicSymbol, Linker, RelocationTarget, Symbol, ABSOLUTE_SECTION,
+};
+use crate::script::{parse_symbol_value, Expression};
+use anyhow::{bail, Context};
+use std::collections::BTreeSet;
+use tracing::info;
+
+/// Symbols locating the ELF header at runtime, defined if referenced
+const HEADER_SYMBOLS: &[&str] = &["__executable_start", "__ehdr_start"];
+
+impl<'a> Linker<'a> {
+    /// Define symbols of --defsym, and of PROVIDE and PROVIDE_HIDDEN in
+    /// linker script only if they are referenced but not defined by input
+    /// files
+    pub(super) fn provide_symbols(&mut self) -> anyhow::Result<()> {
+        // referenced by relocations or as entrypoint
+        let mut referenced: BTreeSet<String> = self
+            .output_sections
+            .values()
+            .flat_map(|output_section| output_section.relocations.iter())
+            .filter_map(|relocation| match &relocation.target {
+                RelocationTarget::Symbol(name) => Some(name.clone()),
+                RelocationTarget::Section(_) => None,
+            })
+            .collect();
+        referenced.insert(
+            self.opt
+                .entry_symbol
+                .clone()
+                .unwrap_or("_start".to_string()),
+        );
+
+        // symbol name, value, hidden
+        let mut definitions: Vec<(String, Expression, bool)> = vec![];
+        for (name, text) in &self.opt.defsym {
+            let (expression, hidden) =
+                parse_symbol_value(text).context(format!("Parsing --defsym {}={}", name, text))?;
+            // overrides definitions in input files
+            definitions.push((name.clone(), expression, hidden));
+        }
+        for assignment in &self.script.assignments {
+            let name = &assignment.name;
+            if self.symbols.contains_key(name)
+                || self.plt_dynamic_symbols.iter().any(|sym| &sym.name == name)
+                || !referenced.contains(name)
+                || definitions.iter().any(|(defined, _, _)| defined == name)
+            {
+                continue;
+            }
+            definitions.push((
+                name.clone(),
+                assignment.expression.clone(),
+                assignment.hidden,
+            ));
+        }
+
+        for name in HEADER_SYMBOLS {
+            if self.symbols.contains_key(*name)
+                || !referenced.contains(*name)
+                || definitions.iter().any(|(defined, _, _)| defined == name)
+            {
+                continue;
+            }
+            info!("Defining symbol {} at ELF header", name);
+            self.symbols.insert(
+                name.to_string(),
+                Symbol {
+                    section_name: ABSOLUTE_SECTION.to_string(),
+                    offset: 0,
+                    symbol_name_string_id: None,
+                    symbol_name_dynamic_string_id: None,
+                    is_global: false,
+                    is_plt: false,
+                    sym_size: 0,
+                    is_hidden: true,
+                    is_ifunc: false,
+                    is_stub: false,
+                },
+            );
+            self.header_symbols.push(name.to_string());
+        }
+
+        let mut exported = false;
+        for (name, expression, hidden) in definitions {
+            let (section_name, offset) = match &expression {
+                Expression::Symbol(target) => match self.symbols.get(target) {
+                    Some(symbol) => (symbol.section_name.clone(), symbol.offset),
+                    None => bail!(
+                        "Undefined symbol {} provided as {}",
+                        demangle(&self.opt, target),
+                        demangle(&self.opt, &name)
+                    ),
+                },
+                // addresses are unknown yet, evaluated after layout
+                _ => {
+                    self.symbol_expressions
+                        .push((name.clone(), expression.clone()));
+                    (ABSOLUTE_SECTION.to_string(), 0)
+                }
+            };
+            info!("Providing symbol {}", name);
+            self.symbols.insert(
+                name.clone(),
+                Symbol {
+                    section_name,
+                    offset,
+                    symbol_name_string_id: None,
+                    symbol_name_dynamic_string_id: None,
+                    // hidden symbols become local in output
+                    is_global: !hidden,
+                    is_plt: false,
+                    sym_size: 0,
+                    is_hidden: hidden,
+                    is_ifunc: false,
+                    is_stub: false,
+                },
+            );
+            if !hidden
+                && (self.opt.shared || self.opt.export_dynamic)
+                && !self.dynamic_symbols.iter().any(|sym| sym.name == name)
+            {
+                self.dynamic_symbols.push(DynamicSymbol { name });
+                exported = true;
+            }
+        }
+        if exported {
+            sort_dynamic_symbols(&mut self.dynamic_symbols);
+        }
+        Ok(())
+    }
+
+    /// Both __executable_start and __ehdr_start are the load address: the
+    /// first PT_LOAD maps the file from offset 0, beginning with the ELF
+    /// header
+    pub(super) fn define_header_symbols(&mut self) {
+        for name in &self.header_symbols {
+            info!("Symbol {} = {:#x}", name, self.load_address);
+            self.symbols.get_mut(name).unwrap().offset = self.load_address;
+        }
+    }
+
+    /// Compute values of provided symbols, once addresses are assigned
+    pub(super) fn evaluate_symbols(&mut self) -> anyhow::Result<()> {
+        for (name, expression) in std::mem::take(&mut self.symbol_expressions) {
+            let value = expression
+                .evaluate(&*self)
+                .context(format!("Evaluating value of {}", name))?;
+            info!("Symbol {} = {:#x}", name, value);
+            self.symbols.get_mut(&name).unwrap().offset = value;
+        }
+        Ok(())
+    }
+}
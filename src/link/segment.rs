@@ -0,0 +1,249 @@
+use super::OutputSection;
+use crate::opt::Opt;
+use object::elf::{PF_R, PF_W, PF_X};
+use std::collections::BTreeMap;
+
+/// A PT_LOAD segment
+pub struct LoadSegment {
+    pub flags: u32,
+    // file offset, also offset from load address unless after an address gap
+    pub offset: u64,
+    // bytes from file
+    pub file_size: u64,
+    // bytes in memory, including zero filled .bss
+    pub memory_size: u64,
+}
+
+/// Where the location counter jumps more than a page ahead: instead of
+/// filling the file up to there, file content from `offset` on is mapped by a
+/// new PT_LOAD, `shift` bytes above load address plus offset
+pub struct AddressGap {
+    pub offset: u64,
+    // multiple of the page size, keeping offset and address congruent
+    pub shift: u64,
+}
+
+/// Address of the given file offset
+pub fn address(load_address: u64, gaps: &[AddressGap], offset: u64) -> u64 {
+    let shift = gaps
+        .iter()
+        .rev()
+        .find(|gap| gap.offset <= offset)
+        .map_or(0, |gap| gap.shift);
+    load_address + offset + shift
+}
+
+/// Sections occupying no file space: they are placed after everything in
+/// the file, at the end of the last segment. .tbss stays in file because it
+/// belongs to the TLS template
+pub fn is_nobits(output_section: &OutputSection) -> bool {
+    output_section.is_bss && !output_section.is_tls
+}
+
+/// Permissions of the segment containing the section: with --rosegment,
+/// read-only data, code and writable data are mapped separately, otherwise
+/// one segment maps them all
+pub fn segment_flags(output_section: &OutputSection, rosegment: bool) -> u32 {
+    if !rosegment {
+        PF_X | PF_W | PF_R
+    } else if output_section.is_executable {
+        PF_X | PF_R
+    } else if output_section.is_writable {
+        PF_W | PF_R
+    } else {
+        PF_R
+    }
+}
+
+/// Permissions of the segment containing the section in output: -z
+/// separate-code alone maps code apart from all data
+fn output_segment_flags(output_section: &OutputSection, opt: &Opt) -> u32 {
+    if opt.separate_code && !opt.rosegment {
+        if output_section.is_executable {
+            PF_X | PF_R
+        } else {
+            PF_W | PF_R
+        }
+    } else {
+        segment_flags(output_section, opt.rosegment)
+    }
+}
+
+/// Order of output sections backed by file content: sections listed in
+/// SECTIONS of linker script come first in that order, the others are
+/// grouped by segment
+pub fn section_order(output_sections: &BTreeMap<String, OutputSection>, opt: &Opt) -> Vec<String> {
+    let mut names: Vec<&String> = output_sections
+        .iter()
+        .filter(|(_name, output_section)| !is_nobits(output_section))
+        .map(|(name, _output_section)| name)
+        .collect();
+    // read-only, then executable, then writable
+    names.sort_by_key(|name| {
+        let output_section = &output_sections[*name];
+        let flags = output_segment_flags(output_section, opt);
+        let group = if flags & PF_X != 0 {
+            1
+        } else if flags & PF_W != 0 {
+            2
+        } else {
+            0
+        };
+        (output_section.script_order.unwrap_or(usize::MAX), group)
+    });
+    names.into_iter().cloned().collect()
+}
+
+/// Sections of OVERLAY in linker script, in file order: each of them gets a
+/// PT_LOAD mapping it from where it is loaded to the address shared by the
+/// overlay
+pub fn overlay_sections(
+    output_sections: &BTreeMap<String, OutputSection>,
+    opt: &Opt,
+) -> Vec<String> {
+    section_order(output_sections, opt)
+        .into_iter()
+        .filter(|name| output_sections[name].overlay.is_some())
+        .collect()
+}
+
+/// Sections getting a PT_LOAD of their own, whose p_paddr is the load
+/// address: sections of OVERLAY, and sections with AT(lma) in linker script
+pub fn separately_loaded_sections(
+    output_sections: &BTreeMap<String, OutputSection>,
+    opt: &Opt,
+) -> Vec<String> {
+    section_order(output_sections, opt)
+        .into_iter()
+        .filter(|name| {
+            let output_section = &output_sections[name];
+            output_section.overlay.is_some() || output_section.load_expression.is_some()
+        })
+        .collect()
+}
+
+/// End of file content of sections, where .rela.*, .dynamic etc. follow.
+/// With -z separate-code they start in a new page, so that they are not
+/// mapped in the code segment
+pub fn sections_end(output_sections: &BTreeMap<String, OutputSection>, opt: &Opt) -> Option<u64> {
+    section_order(output_sections, opt)
+        .iter()
+        .map(|name| output_sections[name].offset + output_sections[name].content.len() as u64)
+        .max()
+        .map(|end| end.next_multiple_of(4096))
+}
+
+/// Segment flags and file offsets where each segment begins, a new one also
+/// after each address gap
+fn segment_starts(
+    output_sections: &BTreeMap<String, OutputSection>,
+    opt: &Opt,
+    gaps: &[AddressGap],
+) -> Vec<(u32, u64)> {
+    let mut starts: Vec<(u32, u64)> = vec![];
+    for name in section_order(output_sections, opt) {
+        let output_section = &output_sections[&name];
+        let flags = output_segment_flags(output_section, opt);
+        let after_gap = gaps.iter().any(|gap| gap.offset == output_section.offset);
+        match starts.last() {
+            Some((last_flags, _)) if *last_flags == flags && !after_gap => {}
+            // headers are never mapped executable with -z separate-code
+            None if opt.separate_code && flags & PF_X != 0 => {
+                starts.push((PF_R, 0));
+                starts.push((flags, output_section.offset));
+            }
+            None if after_gap => {
+                starts.push((flags, 0));
+                starts.push((flags, output_section.offset));
+            }
+            // the first segment also maps the headers
+            None => starts.push((flags, 0)),
+            Some(_) => starts.push((flags, output_section.offset)),
+        }
+    }
+    if starts
+        .last()
+        .is_some_and(|(flags, _)| opt.separate_code && flags & PF_X != 0)
+    {
+        // neither is what is reserved after sections
+        starts.push((PF_R, sections_end(output_sections, opt).unwrap()));
+    }
+    if starts.is_empty() {
+        starts.push((PF_X | PF_W | PF_R, 0));
+    }
+    starts
+}
+
+/// Whether .bss needs its own segment, because the last segment is not
+/// writable
+fn needs_nobits_segment(output_sections: &BTreeMap<String, OutputSection>, opt: &Opt) -> bool {
+    let has_nobits = output_sections.values().any(is_nobits);
+    let last_flags = segment_starts(output_sections, opt, &[]).last().unwrap().0;
+    has_nobits && last_flags & PF_W == 0
+}
+
+/// Number of PT_LOAD segments, not counting those after address gaps
+pub fn load_segments_count(output_sections: &BTreeMap<String, OutputSection>, opt: &Opt) -> usize {
+    segment_starts(output_sections, opt, &[]).len()
+        + needs_nobits_segment(output_sections, opt) as usize
+}
+
+/// Split the file into PT_LOAD segments: each segment maps up to the next
+/// one, the last one maps everything reserved after sections (.rela.*,
+/// .dynamic etc.) and is extended in memory to cover .bss
+pub fn load_segments(
+    output_sections: &BTreeMap<String, OutputSection>,
+    opt: &Opt,
+    gaps: &[AddressGap],
+    file_len: u64,
+) -> Vec<LoadSegment> {
+    let starts = segment_starts(output_sections, opt, gaps);
+    let mut segments: Vec<LoadSegment> = starts
+        .iter()
+        .enumerate()
+        .map(|(index, (flags, offset))| {
+            let end = starts.get(index + 1).map_or(file_len, |next| next.1);
+            LoadSegment {
+                flags: *flags,
+                offset: *offset,
+                file_size: end - offset,
+                memory_size: end - offset,
+            }
+        })
+        .collect();
+
+    let nobits_range = output_sections
+        .values()
+        .filter(|output_section| is_nobits(output_section))
+        .fold(None, |range: Option<(u64, u64)>, output_section| {
+            let start = output_section.offset;
+            let end = output_section.offset + output_section.content.len() as u64;
+            Some(range.map_or((start, end), |(min, max)| (min.min(start), max.max(end))))
+        });
+    if let Some((start, end)) = nobits_range {
+        if needs_nobits_segment(output_sections, opt) {
+            segments.push(LoadSegment {
+                flags: PF_W | PF_R,
+                offset: start,
+                file_size: 0,
+                memory_size: end - start,
+            });
+        } else {
+            let last = segments.last_mut().unwrap();
+            last.memory_size = end - last.offset;
+        }
+    }
+    segments
+}
+
+/// Place sections without file content after the end of file
+pub fn reserve_nobits(output_sections: &mut BTreeMap<String, OutputSection>, file_len: u64) {
+    let mut end = file_len;
+    for output_section in output_sections.values_mut() {
+        if is_nobits(output_section) {
+            // start in a new page, so that file content is not mapped there
+            output_section.offset = end.next_multiple_of(4096);
+            end = output_section.offset + output_section.content.len() as u64;
+        }
+    }
+}
@@ -0,0 +1,315 @@
+use super::{
+    dynamic_symbol_index, DynamicRelocation, Linker, OutputRelocationSection, OutputSection,
+    Relocation, RelocationTarget,
+};
+use anyhow::bail;
+use object::elf::{
+    R_X86_64_DTPMOD64, R_X86_64_DTPOFF64, R_X86_64_GOTPC32_TLSDESC, R_X86_64_GOTTPOFF,
+    R_X86_64_TLSDESC, R_X86_64_TLSGD, R_X86_64_TLSLD, R_X86_64_TPOFF64,
+};
+use object::write::elf::Rel;
+use std::collections::{BTreeMap, BTreeSet};
+use tracing::info;
+
+/// The PT_TLS segment spanning all SHF_TLS output sections
+pub struct TlsSegment {
+    // file offset
+    pub offset: u64,
+    // virtual address
+    pub address: u64,
+    pub size: u64,
+    pub align: u64,
+}
+
+impl TlsSegment {
+    /// Offset of an address in the segment from the thread pointer in
+    /// executables: the TLS block of the executable ends at the thread
+    /// pointer, aligned like the segment
+    pub fn thread_pointer_offset(&self, address: i64) -> i64 {
+        address - (self.address + self.size.next_multiple_of(self.align)) as i64
+    }
+}
+
+/// Compute the TLS segment from the TLS output sections
+pub fn tls_segment(
+    output_sections: &BTreeMap<String, OutputSection>,
+    section_address: &BTreeMap<String, u64>,
+) -> Option<TlsSegment> {
+    let mut tls_sections = output_sections
+        .iter()
+        .filter(|(_name, output_section)| output_section.is_tls);
+    let (first_name, first) = tls_sections.next()?;
+    let mut segment = TlsSegment {
+        offset: first.offset,
+        address: section_address[first_name],
+        size: first.content.len() as u64,
+        align: first.align.max(1),
+    };
+    for (name, output_section) in tls_sections {
+        // sections in between are covered as well
+        segment.size =
+            section_address[name] + output_section.content.len() as u64 - segment.address;
+        segment.align = segment.align.max(output_section.align);
+    }
+    Some(segment)
+}
+
+/// Symbols in TLS sections have STT_TLS type, and their value is the offset
+/// in the TLS segment
+pub fn symbol_type_and_value(
+    output_sections: &BTreeMap<String, OutputSection>,
+    tls_segment: &Option<TlsSegment>,
+    section_name: &str,
+    address: u64,
+) -> (u8, u64) {
+    match (output_sections.get(section_name), tls_segment) {
+        (Some(output_section), Some(tls_segment)) if output_section.is_tls => {
+            (object::elf::STT_TLS, address - tls_segment.address)
+        }
+        _ => (object::elf::STT_NOTYPE, address),
+    }
+}
+
+/// Add a (module, offset) pair to .got, returns its offset in .got. The
+/// symbol comes with its index in .dynsym if resolved by dynamic linker, 0
+/// if it binds locally
+fn add_tls_got_entry(
+    got: &mut OutputSection,
+    output_relocations: &mut BTreeMap<String, OutputRelocationSection>,
+    dynamic: bool,
+    symbol: Option<(&str, u32)>,
+) -> u64 {
+    let r_sym = symbol.map_or(0, |(_, r_sym)| r_sym);
+    let got_offset = got.content.len() as u64;
+
+    // module id
+    if dynamic {
+        // filled by dynamic linker
+        got.content.extend(0u64.to_le_bytes());
+        output_relocations
+            .entry(".rela.dyn".to_string())
+            .or_default()
+            .relocations
+            .push(DynamicRelocation {
+                section_name: ".got".to_string(),
+                rel: Rel {
+                    r_offset: got_offset,
+                    r_sym,
+                    r_type: R_X86_64_DTPMOD64,
+                    r_addend: 0,
+                },
+            });
+    } else {
+        // static executable is always module 1
+        got.content.extend(1u64.to_le_bytes());
+    }
+
+    // offset in tls block of the module: known at link time if the symbol
+    // binds locally
+    got.content.extend(0u64.to_le_bytes());
+    if r_sym != 0 {
+        output_relocations
+            .entry(".rela.dyn".to_string())
+            .or_default()
+            .relocations
+            .push(DynamicRelocation {
+                section_name: ".got".to_string(),
+                rel: Rel {
+                    r_offset: got_offset + 8,
+                    r_sym,
+                    r_type: R_X86_64_DTPOFF64,
+                    r_addend: 0,
+                },
+            });
+    } else if let Some((symbol_name, _)) = symbol {
+        got.relocations.push(Relocation {
+            offset: got_offset + 8,
+            kind: object::RelocationKind::Unknown,
+            encoding: object::RelocationEncoding::Generic,
+            size: 64,
+            addend: 0,
+            r_type: R_X86_64_DTPOFF64,
+            target: RelocationTarget::Symbol(symbol_name.to_string()),
+        });
+    }
+
+    got_offset
+}
+
+impl<'a> Linker<'a> {
+    /// Allocate .got entries for general dynamic, local dynamic, initial exec
+    /// and TLS descriptor models
+    pub(super) fn generate_tls(&mut self) -> anyhow::Result<()> {
+        let Linker {
+            opt,
+            output_sections,
+            output_relocations,
+            symbols,
+            plt_dynamic_symbols,
+            dynamic_symbols,
+            tls_gd_got_offsets,
+            tls_ld_got_offset,
+            tls_desc_got_offsets,
+            tls_ie_got_offsets,
+            ..
+        } = self;
+
+        // find tls symbols accessed via __tls_get_addr or descriptors
+        let mut gd_symbols = BTreeSet::new();
+        let mut ld_used = false;
+        let mut desc_symbols = BTreeSet::new();
+        let mut ie_symbols = BTreeSet::new();
+        for output_section in output_sections.values() {
+            for relocation in &output_section.relocations {
+                match (relocation.r_type, &relocation.target) {
+                    (R_X86_64_TLSGD, RelocationTarget::Symbol(name)) => {
+                        gd_symbols.insert(name.clone());
+                    }
+                    (R_X86_64_TLSGD, RelocationTarget::Section(_)) => {
+                        bail!("R_X86_64_TLSGD against section is unsupported")
+                    }
+                    (R_X86_64_TLSLD, _) => ld_used = true,
+                    (R_X86_64_GOTPC32_TLSDESC, RelocationTarget::Symbol(name)) => {
+                        desc_symbols.insert(name.clone());
+                    }
+                    (R_X86_64_GOTPC32_TLSDESC, RelocationTarget::Section(_)) => {
+                        bail!("R_X86_64_GOTPC32_TLSDESC against section is unsupported")
+                    }
+                    (R_X86_64_GOTTPOFF, RelocationTarget::Symbol(name)) => {
+                        ie_symbols.insert(name.clone());
+                    }
+                    (R_X86_64_GOTTPOFF, RelocationTarget::Section(_)) => {
+                        bail!("R_X86_64_GOTTPOFF against section is unsupported")
+                    }
+                    _ => {}
+                }
+            }
+        }
+        if !opt.shared {
+            // executables access their TLS at a fixed offset from the
+            // thread pointer instead, see relocate
+            desc_symbols.clear();
+        }
+        if gd_symbols.is_empty() && !ld_used && desc_symbols.is_empty() && ie_symbols.is_empty() {
+            return Ok(());
+        }
+
+        // module id is only known at runtime when dynamically linked
+        let dynamic = opt.shared || self.dynamic_link;
+        let got = output_sections
+            .entry(".got".to_string())
+            .or_insert_with(|| OutputSection {
+                name: ".got".to_string(),
+                is_writable: true,
+                ..OutputSection::default()
+            });
+        let dynamic_symbol_index = |name: &str| {
+            dynamic_symbol_index(opt, symbols, plt_dynamic_symbols, dynamic_symbols, name)
+                .unwrap_or(0)
+        };
+        for name in gd_symbols {
+            info!("Allocating TLS GOT entry for symbol {}", name);
+            let r_sym = dynamic_symbol_index(&name);
+            let got_offset =
+                add_tls_got_entry(got, output_relocations, dynamic, Some((&name, r_sym)));
+            tls_gd_got_offsets.insert(name, got_offset);
+        }
+        if ld_used {
+            // offset is added by R_X86_64_DTPOFF32 in code
+            info!("Allocating TLS GOT entry for local dynamic model");
+            *tls_ld_got_offset = Some(add_tls_got_entry(got, output_relocations, dynamic, None));
+        }
+        for name in desc_symbols {
+            info!("Allocating TLS descriptor in GOT for symbol {}", name);
+            let got_offset = got.content.len() as u64;
+            // resolver and argument are filled by dynamic linker, from the
+            // offset in tls block moved into the addend
+            got.content.extend([0; 16]);
+            got.relocations.push(Relocation {
+                offset: got_offset + 8,
+                kind: object::RelocationKind::Unknown,
+                encoding: object::RelocationEncoding::Generic,
+                size: 64,
+                addend: 0,
+                r_type: R_X86_64_DTPOFF64,
+                target: RelocationTarget::Symbol(name.clone()),
+            });
+            output_relocations
+                .entry(".rela.dyn".to_string())
+                .or_default()
+                .relocations
+                .push(DynamicRelocation {
+                    section_name: ".got".to_string(),
+                    rel: Rel {
+                        r_offset: got_offset,
+                        r_sym: 0,
+                        r_type: R_X86_64_TLSDESC,
+                        r_addend: 0,
+                    },
+                });
+            tls_desc_got_offsets.insert(name, got_offset);
+        }
+        for name in ie_symbols {
+            info!(
+                "Allocating TLS GOT entry for symbol {} of initial exec model",
+                name
+            );
+            let got_offset = got.content.len() as u64;
+            got.content.extend([0; 8]);
+            let r_sym = dynamic_symbol_index(&name);
+            if r_sym != 0 {
+                // offset of imported or preemptible symbols is only known
+                // to the dynamic linker
+                output_relocations
+                    .entry(".rela.dyn".to_string())
+                    .or_default()
+                    .relocations
+                    .push(DynamicRelocation {
+                        section_name: ".got".to_string(),
+                        rel: Rel {
+                            r_offset: got_offset,
+                            r_sym,
+                            r_type: R_X86_64_TPOFF64,
+                            r_addend: 0,
+                        },
+                    });
+                tls_ie_got_offsets.insert(name, got_offset);
+                continue;
+            }
+            // executables know the offset from thread pointer at link time,
+            // shared libraries move the offset in tls block into the addend
+            // and ld.so adds the offset of the block
+            got.relocations.push(Relocation {
+                offset: got_offset,
+                kind: object::RelocationKind::Unknown,
+                encoding: object::RelocationEncoding::Generic,
+                size: 64,
+                addend: 0,
+                r_type: if opt.shared {
+                    R_X86_64_DTPOFF64
+                } else {
+                    R_X86_64_TPOFF64
+                },
+                target: RelocationTarget::Symbol(name.clone()),
+            });
+            if opt.shared {
+                output_relocations
+                    .entry(".rela.dyn".to_string())
+                    .or_default()
+                    .relocations
+                    .push(DynamicRelocation {
+                        section_name: ".got".to_string(),
+                        rel: Rel {
+                            r_offset: got_offset,
+                            r_sym: 0,
+                            r_type: R_X86_64_TPOFF64,
+                            r_addend: 0,
+                        },
+                    });
+            }
+            tls_ie_got_offsets.insert(name, got_offset);
+        }
+
+        Ok(())
+    }
+}
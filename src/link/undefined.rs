@@ -0,0 +1,84 @@
+use super::demangle::demangle;
+use super::{user_warning, DynamicSymbol, Linker, RelocationTarget, Symbol, ABSOLUTE_SECTION};
+use crate::opt::UnresolvedSymbols;
+use anyhow::bail;
+use std::collections::BTreeSet;
+use tracing::info;
+
+impl<'a> Linker<'a> {
+    /// Handle symbols referenced by relocations but defined nowhere: shared
+    /// libraries and --undefined-dynamic-lookup leave them to the dynamic
+    /// linker, executables report them according to --unresolved-symbols
+    pub(super) fn resolve_undefined_symbols(&mut self) -> anyhow::Result<()> {
+        let undefined: BTreeSet<String> = self
+            .output_sections
+            .values()
+            .flat_map(|output_section| output_section.relocations.iter())
+            .filter_map(|relocation| match &relocation.target {
+                RelocationTarget::Symbol(name) => Some(name),
+                RelocationTarget::Section(_) => None,
+            })
+            .filter(|name| {
+                !self.symbols.contains_key(*name)
+                    && !self.plt_dynamic_symbols.iter().any(|sym| &sym.name == *name)
+                    // defined when generating .got
+                    && *name != "_GLOBAL_OFFSET_TABLE_"
+            })
+            .cloned()
+            .collect();
+        if undefined.is_empty() {
+            return Ok(());
+        }
+
+        if self.opt.shared || self.opt.unresolved_symbols == UnresolvedSymbols::DynamicLookup {
+            // bound at runtime like symbols of shared libraries, calls go
+            // through .plt
+            for name in undefined {
+                info!("Leaving undefined symbol {} to dynamic linker", name);
+                self.plt_dynamic_symbols.push(DynamicSymbol { name });
+            }
+            self.dynamic_link = true;
+            self.dynamic_section = true;
+            return Ok(());
+        }
+
+        let messages: Vec<String> = undefined
+            .iter()
+            .map(|name| format!("Undefined symbol {}", demangle(&self.opt, name)))
+            .collect();
+        match self.opt.unresolved_symbols {
+            // only references from object files are seen
+            UnresolvedSymbols::ReportAllError | UnresolvedSymbols::IgnoreInSharedLibs => {
+                bail!("{}", messages.join("\n"))
+            }
+            UnresolvedSymbols::ReportAll => {
+                for message in messages {
+                    user_warning(&self.opt, &message);
+                }
+            }
+            UnresolvedSymbols::IgnoreAll => {}
+            UnresolvedSymbols::DynamicLookup => unreachable!("Left to dynamic linker above"),
+        }
+
+        // tolerated symbols resolve to zero
+        for name in undefined {
+            info!("Resolving undefined symbol {} to 0", name);
+            self.symbols.insert(
+                name,
+                Symbol {
+                    section_name: ABSOLUTE_SECTION.to_string(),
+                    offset: 0,
+                    symbol_name_string_id: None,
+                    symbol_name_dynamic_string_id: None,
+                    is_global: true,
+                    is_plt: false,
+                    sym_size: 0,
+                    is_hidden: false,
+                    is_ifunc: false,
+                    is_stub: false,
+                },
+            );
+        }
+        Ok(())
+    }
+}
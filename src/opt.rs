@@ -1,4 +1,7 @@
 use anyhow::{anyhow, bail};
+use object::Endianness;
+use std::collections::BTreeSet;
+use tracing::warn;
 
 /// handle --push-state/--pop-state
 #[derive(Debug, Copy, Clone)]
@@ -52,40 +55,189 @@ impl Default for HashStyle {
     }
 }
 
+/// --compress-debug-sections=none/zlib/zstd
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CompressDebugSections {
+    #[default]
+    None,
+    /// ELFCOMPRESS_ZLIB
+    Zlib,
+    /// ELFCOMPRESS_ZSTD
+    Zstd,
+}
+
+/// --unresolved-symbols=method, --warn-unresolved-symbols,
+/// --error-unresolved-symbols and --undefined-dynamic-lookup
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnresolvedSymbols {
+    /// report as warnings
+    ReportAll,
+    /// ignore-all, ignore-in-object-files
+    IgnoreAll,
+    /// ignore-in-shared-libs
+    IgnoreInSharedLibs,
+    /// report as errors
+    #[default]
+    ReportAllError,
+    /// left to the dynamic linker, like in shared libraries
+    DynamicLookup,
+}
+
+/// -z text, -z notext and --warn-textrel: dynamic relocations in
+/// read-only sections
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextRelPolicy {
+    /// -z notext
+    #[default]
+    Allow,
+    /// --warn-textrel
+    Warn,
+    /// -z text
+    Error,
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct Opt {
+    /// --apply-dynamic-relocs
+    pub apply_dynamic_relocs: bool,
     /// --build-id
     pub build_id: bool,
+    /// --no-check-sections
+    pub no_check_sections: bool,
+    /// -z combreloc
+    pub combreloc: bool,
+    /// -z nodefaultlib
+    pub nodefaultlib: bool,
+    /// -z nodlopen
+    pub nodlopen: bool,
+    /// -z nodelete
+    pub nodelete: bool,
+    /// -z origin
+    pub origin: bool,
+    /// -z now, -z lazy turns it off
+    pub now: bool,
+    /// --compress-debug-sections
+    pub compress_debug_sections: CompressDebugSections,
+    /// --copy-dt-needed-entries
+    pub copy_dt_needed_entries: bool,
+    /// --add-needed, on unless --no-add-needed
+    pub add_needed: bool,
+    /// --defsym symbol=expression
+    pub defsym: Vec<(String, String)>,
+    /// --no-demangle
+    pub no_demangle: bool,
+    /// --disable-new-dtags: DT_RPATH instead of DT_RUNPATH
+    pub disable_new_dtags: bool,
     /// --eh-frame-hdr
     pub eh_frame_hdr: bool,
+    /// --emit-stub-syms
+    pub emit_stub_syms: bool,
+    /// -e entry, --entry=entry: symbol
+    pub entry_symbol: Option<String>,
+    /// -e entry, --entry=entry: hexadecimal address
+    pub entry_address: Option<u64>,
+    /// -E, --export-dynamic
+    pub export_dynamic: bool,
+    /// --exclude-section=pattern
+    pub exclude_sections: Vec<String>,
+    /// --gc-sections
+    pub gc_sections: bool,
+    /// --gc-keep-exported, implied by -shared
+    pub gc_keep_exported: bool,
+    /// --print-gc-sections
+    pub print_gc_sections: bool,
+    /// --print-output-format
+    pub print_output_format: bool,
+    /// --print-output-size
+    pub print_output_size: bool,
+    /// --quiet
+    pub quiet: bool,
     /// -pie
     pub pie: bool,
+    /// --rosegment
+    pub rosegment: bool,
+    /// -z separate-code
+    pub separate_code: bool,
     /// -shared
     pub shared: bool,
+    /// -static-pie
+    pub static_pie: bool,
     /// -m emulation
     pub emulation: Option<String>,
+    /// -EB or -EL: picks the big or little endian OUTPUT_FORMAT
+    pub endianness: Option<Endianness>,
+    /// OUTPUT_FORMAT of linker script
+    pub output_format: Option<String>,
     /// -o output
     pub output: Option<String>,
+    /// -T script, --script=script
+    pub linker_script: Option<String>,
+    /// -Map mapfile
+    pub map_file: Option<String>,
     /// -dynamic-linker
     pub dynamic_linker: Option<String>,
     /// -L searchdir
     pub search_dir: Vec<String>,
+    /// -rpath dir
+    pub rpath: Vec<String>,
+    /// -rpath-link dir
+    pub rpath_link: Vec<String>,
     /// --hash-style=sysv/gnu/both
     pub hash_style: HashStyle,
     /// -soname SONAME
     pub soname: Option<String>,
+    /// -Bsymbolic
+    pub symbolic: bool,
+    /// -Bsymbolic-functions
+    pub symbolic_functions: bool,
+    /// --strip-debug
+    pub strip_debug: bool,
+    /// -z text, -z notext, --warn-textrel
+    pub text_relocs_policy: TextRelPolicy,
+    /// --warn-ifunc-textrel
+    pub warn_ifunc_textrel: bool,
+    /// --unresolved-symbols=method
+    pub unresolved_symbols: UnresolvedSymbols,
+    /// --only-keep-debug
+    pub only_keep_debug: bool,
     /// ObjectFile
     pub obj_file: Vec<ObjectFileOpt>,
 }
 
+/// -e entry accepts a symbol name or a hexadecimal address
+fn parse_entry(opt: &mut Opt, entry: &str) {
+    match entry
+        .strip_prefix("0x")
+        .and_then(|hex| u64::from_str_radix(hex, 16).ok())
+    {
+        Some(address) => opt.entry_address = Some(address),
+        None => opt.entry_symbol = Some(entry.to_string()),
+    }
+}
+
+/// --defsym symbol=expression
+fn parse_defsym(opt: &mut Opt, defsym: &str) -> anyhow::Result<()> {
+    let (symbol, expression) = defsym
+        .split_once('=')
+        .ok_or(anyhow!("Missing = in --defsym {}", defsym))?;
+    opt.defsym
+        .push((symbol.trim().to_string(), expression.to_string()));
+    Ok(())
+}
+
 /// parse arguments
 pub fn parse_opts(args: &[String]) -> anyhow::Result<Opt> {
-    let mut opt = Opt::default();
+    let mut opt = Opt {
+        add_needed: true,
+        ..Opt::default()
+    };
     let mut cur_opt_stack = OptStack {
         as_needed: false,
         link_static: false,
     };
     let mut opt_stack = vec![];
+    // options passed by the GCC driver that are ignored, warned once each
+    let mut ignored = BTreeSet::new();
     let mut iter = args.iter();
     while let Some(arg) = iter.next() {
         match arg.as_str() {
@@ -95,14 +247,31 @@ pub fn parse_opts(args: &[String]) -> anyhow::Result<Opt> {
                 opt.search_dir
                     .push(s.strip_prefix("-L").unwrap().to_string());
             }
-            "-dynamic-linker" => {
+            "-dynamic-linker" | "--dynamic-linker" => {
                 // dynamic linker argument
                 opt.dynamic_linker = Some(
                     iter.next()
-                        .ok_or(anyhow!("Missing dynamic linker after -dynamic-linker"))?
+                        .ok_or(anyhow!("Missing dynamic linker after {}", arg))?
                         .to_string(),
                 );
             }
+            s if s.starts_with("-dynamic-linker=") || s.starts_with("--dynamic-linker=") => {
+                opt.dynamic_linker = Some(s.split_once('=').unwrap().1.to_string());
+            }
+            "-e" | "--entry" => {
+                // entry argument
+                let entry = iter.next().ok_or(anyhow!("Missing entry after {}", arg))?;
+                parse_entry(&mut opt, entry);
+            }
+            s if s.starts_with("--entry=") => {
+                parse_entry(&mut opt, s.strip_prefix("--entry=").unwrap());
+            }
+            "-E" | "--export-dynamic" => {
+                opt.export_dynamic = true;
+            }
+            "--no-export-dynamic" => {
+                opt.export_dynamic = false;
+            }
             s if s.starts_with("-l") => {
                 // library argument
                 opt.obj_file.push(ObjectFileOpt::Library(LibraryOpt {
@@ -119,6 +288,17 @@ pub fn parse_opts(args: &[String]) -> anyhow::Result<Opt> {
                         .to_string(),
                 );
             }
+            "-Map" | "--Map" => {
+                // map file argument
+                opt.map_file = Some(
+                    iter.next()
+                        .ok_or(anyhow!("Missing map file after {}", arg))?
+                        .to_string(),
+                );
+            }
+            s if s.starts_with("-Map=") || s.starts_with("--Map=") => {
+                opt.map_file = Some(s.split_once('=').unwrap().1.to_string());
+            }
             "-o" => {
                 // output argument
                 opt.output = Some(
@@ -127,6 +307,12 @@ pub fn parse_opts(args: &[String]) -> anyhow::Result<Opt> {
                         .to_string(),
                 );
             }
+            "-EB" => {
+                opt.endianness = Some(Endianness::Big);
+            }
+            "-EL" => {
+                opt.endianness = Some(Endianness::Little);
+            }
             "-pie" => {
                 opt.pie = true;
             }
@@ -137,6 +323,56 @@ pub fn parse_opts(args: &[String]) -> anyhow::Result<Opt> {
             s if s.starts_with("-plugin-opt=") => {
                 // ignored
             }
+            "--sysroot" | "--gcc-toolchain" => {
+                // skip path argument
+                iter.next().ok_or(anyhow!("Missing path after {}", arg))?;
+                if ignored.insert(arg.as_str()) {
+                    warn!("{} ignored", arg);
+                }
+            }
+            s if s.starts_with("--sysroot=") || s.starts_with("--gcc-toolchain=") => {
+                let name = s.split_once('=').unwrap().0;
+                if ignored.insert(name) {
+                    warn!("{} ignored", name);
+                }
+            }
+            "-undefined" => {
+                // macOS style -undefined treatment
+                opt.unresolved_symbols = match iter
+                    .next()
+                    .ok_or(anyhow!("Missing treatment after -undefined"))?
+                    .as_str()
+                {
+                    "dynamic_lookup" => UnresolvedSymbols::DynamicLookup,
+                    "error" => UnresolvedSymbols::ReportAllError,
+                    "warning" => UnresolvedSymbols::ReportAll,
+                    "suppress" => UnresolvedSymbols::IgnoreAll,
+                    treatment => bail!("Invalid treatment after -undefined: {}", treatment),
+                };
+            }
+            "-rpath" | "--rpath" => {
+                // rpath argument
+                opt.rpath.push(
+                    iter.next()
+                        .ok_or(anyhow!("Missing directory after {}", arg))?
+                        .to_string(),
+                );
+            }
+            s if s.starts_with("-rpath=") || s.starts_with("--rpath=") => {
+                opt.rpath.push(s.split_once('=').unwrap().1.to_string());
+            }
+            "-rpath-link" => {
+                // rpath-link argument
+                opt.rpath_link.push(
+                    iter.next()
+                        .ok_or(anyhow!("Missing directory after -rpath-link"))?
+                        .to_string(),
+                );
+            }
+            s if s.starts_with("-rpath-link=") || s.starts_with("--rpath-link=") => {
+                opt.rpath_link
+                    .push(s.split_once('=').unwrap().1.to_string());
+            }
             "-shared" => {
                 opt.shared = true;
             }
@@ -148,27 +384,162 @@ pub fn parse_opts(args: &[String]) -> anyhow::Result<Opt> {
                         .to_string(),
                 );
             }
-            "-static" => {
+            "-S" => {
+                opt.strip_debug = true;
+            }
+            "-static-pie" => {
+                // -static -pie --no-dynamic-linker
+                opt.static_pie = true;
+                opt.pie = true;
+                opt.dynamic_linker = None;
                 cur_opt_stack.link_static = true;
             }
+            "-static" | "-Bstatic" | "-dn" | "-non_shared" => {
+                cur_opt_stack.link_static = true;
+            }
+            "-Bdynamic" | "-dy" | "-call_shared" => {
+                cur_opt_stack.link_static = false;
+            }
+            "-Bsymbolic" => {
+                opt.symbolic = true;
+            }
+            "-Bsymbolic-functions" => {
+                opt.symbolic_functions = true;
+            }
+            "-Bno-symbolic" => {
+                opt.symbolic = false;
+                opt.symbolic_functions = false;
+            }
+            "-B" => {
+                // -B static or -B dynamic
+                cur_opt_stack.link_static = match iter
+                    .next()
+                    .ok_or(anyhow!("Missing mode after -B"))?
+                    .as_str()
+                {
+                    "static" => true,
+                    "dynamic" => false,
+                    mode => bail!("Invalid mode after -B: {}", mode),
+                };
+            }
+            "-T" | "--script" => {
+                // linker script argument
+                opt.linker_script = Some(
+                    iter.next()
+                        .ok_or(anyhow!("Missing linker script after {}", arg))?
+                        .to_string(),
+                );
+            }
+            s if s.starts_with("--script=") => {
+                opt.linker_script = Some(s.strip_prefix("--script=").unwrap().to_string());
+            }
             "-z" => {
-                // skip -z argument for now
-                iter.next();
+                // keyword argument
+                match iter
+                    .next()
+                    .ok_or(anyhow!("Missing keyword after -z"))?
+                    .as_str()
+                {
+                    "combreloc" => opt.combreloc = true,
+                    "nocombreloc" => opt.combreloc = false,
+                    "nodefaultlib" => opt.nodefaultlib = true,
+                    "nodlopen" => opt.nodlopen = true,
+                    "nodelete" => opt.nodelete = true,
+                    "origin" => opt.origin = true,
+                    // the last of -z now and -z lazy wins, like GNU ld
+                    "now" => opt.now = true,
+                    "lazy" => opt.now = false,
+                    "separate-code" => opt.separate_code = true,
+                    "noseparate-code" => opt.separate_code = false,
+                    "text" => opt.text_relocs_policy = TextRelPolicy::Error,
+                    "notext" | "textoff" => opt.text_relocs_policy = TextRelPolicy::Allow,
+                    // skip other keywords for now
+                    _ => {}
+                }
             }
 
             // double dashes
+            "--apply-dynamic-relocs" => {
+                opt.apply_dynamic_relocs = true;
+            }
+            "--no-apply-dynamic-relocs" => {
+                opt.apply_dynamic_relocs = false;
+            }
             "--as-needed" => {
                 cur_opt_stack.as_needed = true;
             }
+            "--no-as-needed" => {
+                cur_opt_stack.as_needed = false;
+            }
             "--build-id" => {
                 opt.build_id = true;
             }
+            "--check-sections" => {
+                opt.no_check_sections = false;
+            }
+            "--no-check-sections" => {
+                opt.no_check_sections = true;
+            }
+            "--add-needed" => {
+                opt.add_needed = true;
+            }
+            "--no-add-needed" => {
+                opt.add_needed = false;
+            }
+            "--copy-dt-needed-entries" => {
+                opt.copy_dt_needed_entries = true;
+            }
+            "--no-copy-dt-needed-entries" => {
+                opt.copy_dt_needed_entries = false;
+            }
+            s if s.starts_with("--compress-debug-sections=") => {
+                opt.compress_debug_sections = match s {
+                    "--compress-debug-sections=none" => CompressDebugSections::None,
+                    "--compress-debug-sections=zlib" => CompressDebugSections::Zlib,
+                    "--compress-debug-sections=zstd" => CompressDebugSections::Zstd,
+                    _ => bail!("Invalid --compress-debug-sections option: {}", s),
+                };
+            }
+            "--defsym" => {
+                let defsym = iter
+                    .next()
+                    .ok_or(anyhow!("Missing symbol after --defsym"))?;
+                parse_defsym(&mut opt, defsym)?;
+            }
+            s if s.starts_with("--defsym=") => {
+                parse_defsym(&mut opt, s.strip_prefix("--defsym=").unwrap())?;
+            }
+            "--demangle" => {
+                opt.no_demangle = false;
+            }
+            "--no-demangle" => {
+                opt.no_demangle = true;
+            }
+            "--disable-new-dtags" => {
+                opt.disable_new_dtags = true;
+            }
+            "--enable-new-dtags" => {
+                opt.disable_new_dtags = false;
+            }
             "--eh-frame-hdr" => {
                 opt.eh_frame_hdr = true;
             }
+            "--emit-stub-syms" => {
+                opt.emit_stub_syms = true;
+            }
             "--end-group" => {
                 opt.obj_file.push(ObjectFileOpt::EndGroup);
             }
+            s if s.starts_with("--exclude-section=") => {
+                opt.exclude_sections
+                    .push(s.strip_prefix("--exclude-section=").unwrap().to_string());
+            }
+            "--gc-keep-exported" => {
+                opt.gc_keep_exported = true;
+            }
+            "--gc-sections" => {
+                opt.gc_sections = true;
+            }
             s if s.starts_with("--hash-style=") => match s {
                 "--hash-style=sysv" => {
                     opt.hash_style.sysv = true;
@@ -186,9 +557,90 @@ pub fn parse_opts(args: &[String]) -> anyhow::Result<Opt> {
                     bail!("Invalid --hash-style option: {}", s)
                 }
             },
+            "--no-gc-sections" => {
+                opt.gc_sections = false;
+            }
             "--start-group" => {
                 opt.obj_file.push(ObjectFileOpt::StartGroup);
             }
+            "--print-gc-sections" => {
+                opt.print_gc_sections = true;
+            }
+            "--no-print-gc-sections" => {
+                opt.print_gc_sections = false;
+            }
+            "--print-icf-sections" => {
+                bail!("{} is unsupported: --icf is not implemented", arg)
+            }
+            "--no-print-icf-sections" => {
+                // identical sections are never folded, nothing is reported
+            }
+            "--only-keep-debug" => {
+                opt.only_keep_debug = true;
+            }
+            "--strip-debug" => {
+                opt.strip_debug = true;
+            }
+            "--print-output-format" => {
+                opt.print_output_format = true;
+            }
+            "--print-output-size" => {
+                opt.print_output_size = true;
+            }
+            "--quiet" => {
+                opt.quiet = true;
+            }
+            "--no-dynamic-linker" => {
+                opt.dynamic_linker = None;
+            }
+            "--rosegment" => {
+                opt.rosegment = true;
+            }
+            "--no-rosegment" => {
+                opt.rosegment = false;
+            }
+            s if s.starts_with("--unresolved-symbols=") => {
+                opt.unresolved_symbols = match s {
+                    "--unresolved-symbols=report-all" => UnresolvedSymbols::ReportAllError,
+                    "--unresolved-symbols=ignore-all" => UnresolvedSymbols::IgnoreAll,
+                    // references in shared libraries are not checked
+                    "--unresolved-symbols=ignore-in-object-files" => UnresolvedSymbols::IgnoreAll,
+                    "--unresolved-symbols=ignore-in-shared-libs" => {
+                        UnresolvedSymbols::IgnoreInSharedLibs
+                    }
+                    _ => bail!("Invalid --unresolved-symbols option: {}", s),
+                };
+            }
+            "--warn-unresolved-symbols" => {
+                opt.unresolved_symbols = UnresolvedSymbols::ReportAll;
+            }
+            "--error-unresolved-symbols" => {
+                opt.unresolved_symbols = UnresolvedSymbols::ReportAllError;
+            }
+            "--undefined-dynamic-lookup" => {
+                opt.unresolved_symbols = UnresolvedSymbols::DynamicLookup;
+            }
+            "--ignore-data-address-equality" => {
+                // only relaxes the folding of identical sections
+                bail!("{} is unsupported: --icf is not implemented", arg)
+            }
+            "--merge-exidx-entries" | "--no-merge-exidx-entries" => {
+                // .ARM.exidx only exists in ARM objects
+                bail!("{} is unsupported: ARM targets are not supported", arg)
+            }
+            "--toc-optimize" | "--no-toc-optimize" => {
+                // the TOC only exists in PowerPC64 objects
+                bail!(
+                    "{} is unsupported: PowerPC64 targets are not supported",
+                    arg
+                )
+            }
+            "--warn-textrel" => {
+                opt.text_relocs_policy = TextRelPolicy::Warn;
+            }
+            "--warn-ifunc-textrel" => {
+                opt.warn_ifunc_textrel = true;
+            }
             "--pop-state" => {
                 cur_opt_stack = opt_stack.pop().unwrap();
             }
@@ -213,6 +665,7 @@ pub fn parse_opts(args: &[String]) -> anyhow::Result<Opt> {
 }
 
 #[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
 mod tests {
     use super::*;
     #[test]
@@ -249,4 +702,90 @@ mod tests {
             assert!(false);
         }
     }
+
+    #[test]
+    fn test_link_static() {
+        let opts = parse_opts(&[
+            "-Bstatic".to_string(),
+            "-la".to_string(),
+            "-B".to_string(),
+            "dynamic".to_string(),
+            "-lb".to_string(),
+            "-B".to_string(),
+            "static".to_string(),
+            "-lc".to_string(),
+            "-Bdynamic".to_string(),
+            "-ld".to_string(),
+        ])
+        .unwrap();
+
+        let link_static: Vec<bool> = opts
+            .obj_file
+            .iter()
+            .map(|obj_file| match obj_file {
+                ObjectFileOpt::Library(lib) => lib.link_static,
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(link_static, [true, false, true, false]);
+        assert!(parse_opts(&["-B".to_string(), "shared".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_gcc_driver_options() {
+        let args: Vec<String> = [
+            "--sysroot=/",
+            "--gcc-toolchain",
+            "/usr",
+            "--gcc-toolchain=/usr",
+            "a.o",
+        ]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+        let opt = parse_opts(&args).unwrap();
+        assert_eq!(opt.obj_file.len(), 1);
+        assert!(parse_opts(&["--sysroot".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_add_needed() {
+        let parse = |args: &[&str]| {
+            let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+            parse_opts(&args).unwrap().add_needed
+        };
+        assert!(parse(&[]));
+        assert!(!parse(&["--no-add-needed"]));
+        assert!(parse(&["--no-add-needed", "--add-needed"]));
+    }
+
+    #[test]
+    fn test_unsupported_options() {
+        for arg in [
+            "--merge-exidx-entries",
+            "--no-merge-exidx-entries",
+            "--ignore-data-address-equality",
+            "--print-icf-sections",
+            "--toc-optimize",
+            "--no-toc-optimize",
+        ] {
+            assert!(parse_opts(&[arg.to_string()]).is_err());
+        }
+        assert!(parse_opts(&["--no-print-icf-sections".to_string()]).is_ok());
+    }
+
+    #[test]
+    fn test_now_lazy() {
+        let parse = |keywords: &[&str]| {
+            let args: Vec<String> = keywords
+                .iter()
+                .flat_map(|keyword| ["-z".to_string(), keyword.to_string()])
+                .collect();
+            parse_opts(&args).unwrap().now
+        };
+        assert!(!parse(&[]));
+        assert!(parse(&["now"]));
+        assert!(!parse(&["now", "lazy"]));
+        assert!(parse(&["lazy", "now"]));
+    }
 }
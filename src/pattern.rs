@@ -0,0 +1,108 @@
+/// Match name against a glob pattern, supporting `*`, `?` and `[...]` like
+/// wildcard patterns in GNU ld
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.as_bytes();
+    let name = name.as_bytes();
+
+    // position to backtrack to after the last `*`
+    let mut star: Option<(usize, usize)> = None;
+    let mut p = 0;
+    let mut n = 0;
+    while n < name.len() {
+        if p < pattern.len() {
+            match pattern[p] {
+                b'*' => {
+                    star = Some((p, n));
+                    p += 1;
+                    continue;
+                }
+                b'?' => {
+                    p += 1;
+                    n += 1;
+                    continue;
+                }
+                b'[' => {
+                    if let Some((matched, len)) = match_class(&pattern[p..], name[n]) {
+                        if matched {
+                            p += len;
+                            n += 1;
+                            continue;
+                        }
+                    } else if name[n] == b'[' {
+                        // unterminated class matches literally
+                        p += 1;
+                        n += 1;
+                        continue;
+                    }
+                }
+                c if c == name[n] => {
+                    p += 1;
+                    n += 1;
+                    continue;
+                }
+                _ => {}
+            }
+        }
+
+        // mismatch: let the last `*` consume one more character
+        match star {
+            Some((star_p, star_n)) => {
+                star = Some((star_p, star_n + 1));
+                p = star_p + 1;
+                n = star_n + 1;
+            }
+            None => return false,
+        }
+    }
+
+    // remaining pattern must be all `*`
+    pattern[p..].iter().all(|c| *c == b'*')
+}
+
+/// Match a character against `[...]` at the start of pattern, returns
+/// whether it matches and the length of the class, or None if unterminated
+fn match_class(pattern: &[u8], c: u8) -> Option<(bool, usize)> {
+    let mut i = 1;
+    let negate = matches!(pattern.get(i), Some(b'!') | Some(b'^'));
+    if negate {
+        i += 1;
+    }
+
+    let mut matched = false;
+    let mut first = true;
+    loop {
+        let start = *pattern.get(i)?;
+        if start == b']' && !first {
+            return Some((matched != negate, i + 1));
+        }
+        first = false;
+        if pattern.get(i + 1) == Some(&b'-') && pattern.get(i + 2).is_some_and(|c| *c != b']') {
+            // character range
+            let end = pattern[i + 2];
+            matched |= start <= c && c <= end;
+            i += 3;
+        } else {
+            matched |= start == c;
+            i += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match(".comment", ".comment"));
+        assert!(!glob_match(".comment", ".comments"));
+        assert!(glob_match(".note.*", ".note.GNU-stack"));
+        assert!(!glob_match(".note.*", ".text"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match(".text.*.cold", ".text.foo.bar.cold"));
+        assert!(glob_match(".data?", ".data1"));
+        assert!(!glob_match(".data?", ".data"));
+        assert!(glob_match(".rodata.[a-c]*", ".rodata.bar"));
+        assert!(!glob_match(".rodata.[!a-c]*", ".rodata.bar"));
+        assert!(glob_match("[", "["));
+    }
+}
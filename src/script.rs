@@ -0,0 +1,1222 @@
+use crate::opt::{FileOpt, LibraryOpt, ObjectFileOpt, Opt};
+use crate::pattern::glob_match;
+use anyhow::{anyhow, bail, Context};
+pub use expression::{BinaryOperator, Environment, Expression, NoLayout, UnaryOperator};
+use object::Endianness;
+use std::path::Path;
+
+mod expression;
+
+/// Output section name of input sections to be dropped
+pub const DISCARD: &str = "/DISCARD/";
+
+/// Order of input sections matched by SORT_BY_NAME(...) or
+/// SORT_BY_ALIGNMENT(...)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SortOrder {
+    /// SORT_BY_NAME or SORT: lexicographically by section name
+    Name,
+    /// SORT_BY_ALIGNMENT: by descending alignment
+    Alignment,
+}
+
+/// `filepattern(sectionpattern ...)` in an output section description
+#[derive(Debug, Clone)]
+pub struct InputSectionDescription {
+    pub file_pattern: String,
+    pub section_patterns: Vec<String>,
+    /// KEEP(...): matched sections are roots of --gc-sections
+    pub keep: bool,
+    /// SORT_BY_NAME(...) or SORT_BY_ALIGNMENT(...), the outermost if nested
+    pub sort: Option<SortOrder>,
+}
+
+/// SORT_BY_NAME and friends
+fn sort_order(keyword: &str) -> Option<SortOrder> {
+    match keyword {
+        "SORT" | "SORT_BY_NAME" => Some(SortOrder::Name),
+        "SORT_BY_ALIGNMENT" => Some(SortOrder::Alignment),
+        _ => None,
+    }
+}
+
+/// `name : { ... } > region` in SECTIONS
+#[derive(Debug, Clone)]
+pub struct OutputSectionDescription {
+    pub name: String,
+    pub input_sections: Vec<InputSectionDescription>,
+    /// `> region`
+    pub region: Option<String>,
+    /// `AT> region`
+    pub load_region: Option<String>,
+    /// `AT(lma)`: load address, evaluated after layout
+    pub load_address: Option<Expression>,
+    /// index of the OVERLAY containing this section, sections in one
+    /// overlay share the same address
+    pub overlay: Option<usize>,
+    /// `FILL(value)` or `= value`: byte in gaps between input sections
+    pub fill: Option<u8>,
+    /// `ALIGN(align)`: the section starts at a multiple of it
+    pub align: Option<u64>,
+}
+
+/// `name (attributes) : ORIGIN = origin, LENGTH = length` in MEMORY
+#[derive(Debug, Clone)]
+pub struct MemoryRegion {
+    pub name: String,
+    pub attributes: String,
+    pub origin: u64,
+    pub length: u64,
+}
+
+/// `PROVIDE(symbol = expression)` or `PROVIDE_HIDDEN(symbol = expression)`
+#[derive(Debug, Clone)]
+pub struct SymbolAssignment {
+    pub name: String,
+    pub expression: Expression,
+    /// PROVIDE_HIDDEN: STV_HIDDEN, not exported
+    pub hidden: bool,
+}
+
+/// `. = expression` in SECTIONS, evaluated while sections are placed
+#[derive(Debug, Clone)]
+pub struct LocationAssignment {
+    pub expression: Expression,
+    /// index in SECTIONS of the output section following the assignment
+    pub section: usize,
+}
+
+/// `ASSERT(condition, message)`, checked after layout
+#[derive(Debug, Clone)]
+pub struct Assertion {
+    pub condition: Expression,
+    pub message: String,
+}
+
+/// A subset of the GNU ld linker script language
+#[derive(Debug, Clone, Default)]
+pub struct Script {
+    /// ENTRY(symbol)
+    pub entry: Option<String>,
+    /// OUTPUT(file)
+    pub output: Option<String>,
+    /// OUTPUT_FORMAT(default) or OUTPUT_FORMAT(default, big, little)
+    pub output_formats: Vec<String>,
+    /// SEARCH_DIR(path)
+    pub search_dirs: Vec<String>,
+    /// INPUT(file ...) and GROUP(file ...), like command line arguments:
+    /// `-lname` for libraries, GROUP is enclosed in `-(` and `-)`
+    pub inputs: Vec<String>,
+    /// SECTIONS { ... }
+    pub sections: Vec<OutputSectionDescription>,
+    /// MEMORY { ... }
+    pub memory: Vec<MemoryRegion>,
+    /// PROVIDE(...) and PROVIDE_HIDDEN(...), in script order
+    pub assignments: Vec<SymbolAssignment>,
+    /// `. = expression` and `. += expression`, in script order
+    pub location_assignments: Vec<LocationAssignment>,
+    /// ASSERT(...)
+    pub assertions: Vec<Assertion>,
+}
+
+impl Script {
+    /// The first output section and input section description of
+    /// SECTIONS matching an input section
+    fn input_section_rule(
+        &self,
+        file_name: &str,
+        section_name: &str,
+    ) -> Option<(&OutputSectionDescription, &InputSectionDescription)> {
+        self.sections.iter().find_map(|output_section| {
+            output_section
+                .input_sections
+                .iter()
+                .find(|input_section| {
+                    glob_match(&input_section.file_pattern, file_name)
+                        && input_section
+                            .section_patterns
+                            .iter()
+                            .any(|pattern| glob_match(pattern, section_name))
+                })
+                .map(|input_section| (output_section, input_section))
+        })
+    }
+
+    /// Output section for an input section, None if no SECTIONS rule
+    /// matches so that the section keeps its own name
+    pub fn output_section_name(&self, file_name: &str, section_name: &str) -> Option<&str> {
+        self.input_section_rule(file_name, section_name)
+            .map(|(output_section, _)| output_section.name.as_str())
+    }
+
+    /// Whether the input section is matched by KEEP(...)
+    pub fn keeps(&self, file_name: &str, section_name: &str) -> bool {
+        self.input_section_rule(file_name, section_name)
+            .is_some_and(|(_, input_section)| input_section.keep)
+    }
+
+    /// Sort order of the input section description matching an input
+    /// section
+    pub fn input_section_sort(&self, file_name: &str, section_name: &str) -> Option<SortOrder> {
+        self.input_section_rule(file_name, section_name)
+            .and_then(|(_, input_section)| input_section.sort)
+    }
+
+    /// Find memory region by name
+    pub fn memory_region(&self, name: &str) -> Option<&MemoryRegion> {
+        self.memory.iter().find(|region| region.name == name)
+    }
+
+    /// Index of the OVERLAY containing the output section
+    pub fn overlay(&self, name: &str) -> Option<usize> {
+        self.sections
+            .iter()
+            .find(|output_section| output_section.name == name)
+            .and_then(|output_section| output_section.overlay)
+    }
+
+    /// Expression of AT(lma) of the output section
+    pub fn load_address(&self, name: &str) -> Option<&Expression> {
+        self.sections
+            .iter()
+            .find(|output_section| output_section.name == name)
+            .and_then(|output_section| output_section.load_address.as_ref())
+    }
+
+    /// Byte filling gaps in the output section
+    pub fn fill(&self, name: &str) -> Option<u8> {
+        self.sections
+            .iter()
+            .find(|output_section| output_section.name == name)
+            .and_then(|output_section| output_section.fill)
+    }
+
+    /// Alignment of ALIGN(align) of the output section
+    pub fn align(&self, name: &str) -> Option<u64> {
+        self.sections
+            .iter()
+            .find(|output_section| output_section.name == name)
+            .and_then(|output_section| output_section.align)
+    }
+
+    /// Position of the output section in SECTIONS
+    pub fn section_order(&self, name: &str) -> Option<usize> {
+        self.sections
+            .iter()
+            .position(|output_section| output_section.name == name)
+    }
+
+    /// Merge commands of the script into options, command line takes
+    /// precedence
+    pub fn apply(&self, opt: &Opt) -> Opt {
+        let mut opt = opt.clone();
+        if opt.entry_symbol.is_none() && opt.entry_address.is_none() {
+            opt.entry_symbol = self.entry.clone();
+        }
+        if opt.output.is_none() {
+            // a.out when neither -o nor OUTPUT names it, like GNU ld
+            opt.output = Some(self.output.clone().unwrap_or("a.out".to_string()));
+        }
+        // -EB and -EL pick the big and little endian formats
+        opt.output_format = match (opt.endianness, self.output_formats.as_slice()) {
+            (Some(Endianness::Big), [_, big, _]) => Some(big.clone()),
+            (Some(Endianness::Little), [_, _, little]) => Some(little.clone()),
+            (_, [default, ..]) => Some(default.clone()),
+            (_, []) => None,
+        };
+        // searched before -L paths, in script order
+        opt.search_dir
+            .splice(0..0, self.search_dirs.iter().cloned());
+        for input in &self.inputs {
+            opt.obj_file.push(match input.strip_prefix("-l") {
+                _ if input == "-(" => ObjectFileOpt::StartGroup,
+                _ if input == "-)" => ObjectFileOpt::EndGroup,
+                Some(name) => ObjectFileOpt::Library(LibraryOpt {
+                    name: name.to_string(),
+                    as_needed: false,
+                    link_static: false,
+                }),
+                None => ObjectFileOpt::File(FileOpt {
+                    name: input.clone(),
+                    as_needed: false,
+                }),
+            });
+        }
+        opt
+    }
+}
+
+/// Parse a number in decimal or hexadecimal, with optional K or M suffix
+fn parse_number(token: &str) -> anyhow::Result<u64> {
+    let (digits, multiplier) = match token.as_bytes().last() {
+        Some(b'K' | b'k') => (&token[..token.len() - 1], 1024),
+        Some(b'M' | b'm') => (&token[..token.len() - 1], 1024 * 1024),
+        _ => (token, 1),
+    };
+    let value = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => digits.parse::<u64>(),
+    }
+    .context(format!("Invalid number {} in linker script", token))?;
+    Ok(value * multiplier)
+}
+
+/// Punctuations that end a word in commands, file names and patterns
+const PUNCTUATIONS: &[char] = &['{', '}', '(', ')', ';', ':', ',', '=', '>'];
+
+/// Operators in expressions, longer ones first
+const OPERATORS: &[&str] = &[
+    "||", "&&", "==", "!=", "<=", ">=", "<<", ">>", "|", "^", "&", "<", ">", "+", "-", "*", "/",
+    "%", "!", "~", "?", ":", "(", ")", ",", ";", "{", "}", "=",
+];
+
+/// Tokens are split on demand: `*` and `/` belong to patterns and file names
+/// in commands, but are operators in expressions
+struct Parser {
+    text: String,
+    // offset of the next token
+    offset: usize,
+}
+
+impl Parser {
+    /// Skip whitespace and comments, returns offset of the next token
+    fn skip_blank(&self) -> anyhow::Result<usize> {
+        let mut offset = self.offset;
+        loop {
+            let rest = &self.text[offset..];
+            let trimmed = rest.trim_start();
+            offset += rest.len() - trimmed.len();
+            match trimmed.strip_prefix("/*") {
+                Some(comment) => {
+                    let end = comment
+                        .find("*/")
+                        .ok_or(anyhow!("Unterminated comment in linker script"))?;
+                    offset += 2 + end + 2;
+                }
+                None => return Ok(offset),
+            }
+        }
+    }
+
+    /// Find the next token and the offset after it
+    fn lex(&self, expression: bool) -> anyhow::Result<Option<(String, usize)>> {
+        let start = self.skip_blank()?;
+        let rest = &self.text[start..];
+        if rest.is_empty() {
+            return Ok(None);
+        }
+        if let Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted
+                .find('"')
+                .ok_or(anyhow!("Unterminated string in linker script"))?;
+            return Ok(Some((quoted[..end].to_string(), start + end + 2)));
+        }
+        let len = if expression {
+            match OPERATORS
+                .iter()
+                .find(|operator| rest.starts_with(**operator))
+            {
+                Some(operator) => operator.len(),
+                None => match rest.find(|c: char| !(c.is_alphanumeric() || "_.$".contains(c))) {
+                    // unknown character
+                    Some(0) => rest.chars().next().unwrap().len_utf8(),
+                    Some(end) => end,
+                    None => rest.len(),
+                },
+            }
+        } else if rest.starts_with(PUNCTUATIONS) {
+            1
+        } else {
+            rest.find(|c: char| c.is_whitespace() || PUNCTUATIONS.contains(&c) || c == '"')
+                .unwrap_or(rest.len())
+        };
+        Ok(Some((rest[..len].to_string(), start + len)))
+    }
+
+    fn next_token(&mut self, expression: bool) -> anyhow::Result<String> {
+        let (token, offset) = self
+            .lex(expression)?
+            .ok_or(anyhow!("Unexpected end of linker script"))?;
+        self.offset = offset;
+        Ok(token)
+    }
+
+    fn peek(&self) -> Option<String> {
+        self.lex(false).ok().flatten().map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> anyhow::Result<String> {
+        self.next_token(false)
+    }
+
+    fn expect(&mut self, expected: &str) -> anyhow::Result<()> {
+        let token = self.next()?;
+        if token != expected {
+            bail!("Expected {} in linker script, found {}", expected, token);
+        }
+        Ok(())
+    }
+
+    fn peek_expression(&self) -> Option<String> {
+        self.lex(true).ok().flatten().map(|(token, _)| token)
+    }
+
+    fn next_expression(&mut self) -> anyhow::Result<String> {
+        self.next_token(true)
+    }
+
+    fn expect_expression(&mut self, expected: &str) -> anyhow::Result<()> {
+        let token = self.next_expression()?;
+        if token != expected {
+            bail!("Expected {} in linker script, found {}", expected, token);
+        }
+        Ok(())
+    }
+
+    /// Consume the next token if it is the given punctuation
+    fn accept(&mut self, punctuation: &str) -> anyhow::Result<bool> {
+        if self.peek().as_deref() == Some(punctuation) {
+            self.next()?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// `(arg)`
+    fn parse_argument(&mut self) -> anyhow::Result<String> {
+        self.expect("(")?;
+        let argument = self.next()?;
+        self.expect(")")?;
+        Ok(argument)
+    }
+
+    /// `(arg arg, ...)`, arguments separated by spaces or commas
+    fn parse_arguments(&mut self) -> anyhow::Result<Vec<String>> {
+        self.expect("(")?;
+        let mut arguments = vec![];
+        loop {
+            match self.next()?.as_str() {
+                ")" => break,
+                "," => {}
+                argument => arguments.push(argument.to_string()),
+            }
+        }
+        Ok(arguments)
+    }
+
+    /// `(sectionpattern ...)`, patterns may be wrapped in SORT_BY_NAME(...)
+    /// or SORT_BY_ALIGNMENT(...)
+    fn parse_section_patterns(&mut self) -> anyhow::Result<(Vec<String>, Option<SortOrder>)> {
+        self.expect("(")?;
+        let mut patterns = vec![];
+        let mut sort = None;
+        loop {
+            let token = self.next()?;
+            match token.as_str() {
+                ")" => break,
+                "," => {}
+                keyword if sort_order(keyword).is_some() && self.peek().as_deref() == Some("(") => {
+                    let (inner_patterns, inner_sort) = self.parse_section_patterns()?;
+                    patterns.extend(inner_patterns);
+                    sort = sort.or(sort_order(keyword)).or(inner_sort);
+                }
+                pattern => patterns.push(pattern.to_string()),
+            }
+        }
+        Ok((patterns, sort))
+    }
+
+    fn parse_input_section(&mut self, keep: bool) -> anyhow::Result<InputSectionDescription> {
+        let file_pattern = self.next()?;
+        if file_pattern == "KEEP" {
+            self.expect("(")?;
+            let input_section = self.parse_input_section(true)?;
+            self.expect(")")?;
+            return Ok(input_section);
+        }
+        if let Some(sort) = sort_order(&file_pattern) {
+            // SORT(*(...)) around the whole description
+            self.expect("(")?;
+            let mut input_section = self.parse_input_section(keep)?;
+            self.expect(")")?;
+            input_section.sort = Some(sort);
+            return Ok(input_section);
+        }
+        // a file name alone takes all its sections
+        let (section_patterns, sort) = if self.peek().as_deref() == Some("(") {
+            self.parse_section_patterns()?
+        } else {
+            (vec!["*".to_string()], None)
+        };
+        Ok(InputSectionDescription {
+            file_pattern,
+            section_patterns,
+            keep,
+            sort,
+        })
+    }
+
+    /// Value of a symbol: an expression, `HIDDEN(expression)` makes the
+    /// symbol STV_HIDDEN
+    fn parse_symbol_value(&mut self) -> anyhow::Result<(Expression, bool)> {
+        if self.peek_expression().as_deref() != Some("HIDDEN") {
+            return Ok((self.parse_expression()?, false));
+        }
+        self.next_expression()?;
+        self.expect_expression("(")?;
+        let expression = self.parse_expression()?;
+        self.expect_expression(")")?;
+        Ok((expression, true))
+    }
+
+    /// `(symbol = expression)` of PROVIDE and PROVIDE_HIDDEN
+    fn parse_provide(&mut self, hidden: bool) -> anyhow::Result<SymbolAssignment> {
+        self.expect("(")?;
+        let name = self.next()?;
+        self.expect("=")?;
+        let (expression, value_hidden) = self.parse_symbol_value()?;
+        self.expect(")")?;
+        Ok(SymbolAssignment {
+            name,
+            expression,
+            hidden: hidden || value_hidden,
+        })
+    }
+
+    /// `(condition, message)` of ASSERT
+    fn parse_assert(&mut self) -> anyhow::Result<Assertion> {
+        self.expect("(")?;
+        let condition = self.parse_expression()?;
+        self.expect(",")?;
+        let message = self.next()?;
+        self.expect(")")?;
+        Ok(Assertion { condition, message })
+    }
+
+    fn parse_sections(&mut self, script: &mut Script) -> anyhow::Result<()> {
+        self.expect("{")?;
+        loop {
+            let name = self.next()?;
+            match name.as_str() {
+                "}" => break,
+                ";" => continue,
+                "PROVIDE" | "PROVIDE_HIDDEN" => {
+                    let assignment = self.parse_provide(name == "PROVIDE_HIDDEN")?;
+                    script.assignments.push(assignment);
+                    continue;
+                }
+                "ASSERT" => {
+                    let assertion = self.parse_assert()?;
+                    script.assertions.push(assertion);
+                    continue;
+                }
+                "OVERLAY" => {
+                    self.parse_overlay(script)?;
+                    continue;
+                }
+                "." => {
+                    // `. += expression` advances from the location counter
+                    let advance = self.accept("+")?;
+                    self.expect("=")?;
+                    let mut expression = self.parse_expression()?;
+                    if advance {
+                        expression = Expression::Binary(
+                            Box::new(Expression::Location),
+                            BinaryOperator::Add,
+                            Box::new(expression),
+                        );
+                    }
+                    script.location_assignments.push(LocationAssignment {
+                        expression,
+                        section: script.sections.len(),
+                    });
+                    continue;
+                }
+                _ => {}
+            }
+            self.expect(":")?;
+            let load_address = if self.accept("AT")? {
+                self.expect("(")?;
+                let expression = self.parse_expression()?;
+                self.expect(")")?;
+                Some(expression)
+            } else {
+                None
+            };
+            let align = if self.accept("ALIGN")? {
+                self.expect("(")?;
+                let align = self.parse_expression()?.evaluate(&NoLayout)?;
+                self.expect(")")?;
+                if align == 0 {
+                    bail!("Alignment of section {} is 0", name);
+                }
+                Some(align)
+            } else {
+                None
+            };
+            let (input_sections, mut fill) = self.parse_section_contents()?;
+            let region = if self.accept(">")? {
+                Some(self.next()?)
+            } else {
+                None
+            };
+            let load_region = if self.accept("AT")? {
+                self.expect(">")?;
+                Some(self.next()?)
+            } else {
+                None
+            };
+            if self.accept("=")? {
+                fill = Some(self.parse_fill()?);
+            }
+            script.sections.push(OutputSectionDescription {
+                name,
+                input_sections,
+                region,
+                load_region,
+                load_address,
+                overlay: None,
+                fill,
+                align,
+            });
+        }
+        Ok(())
+    }
+
+    /// `{ input section descriptions }` of an output section, with the
+    /// value of FILL if any
+    fn parse_section_contents(
+        &mut self,
+    ) -> anyhow::Result<(Vec<InputSectionDescription>, Option<u8>)> {
+        self.expect("{")?;
+        let mut input_sections = vec![];
+        let mut fill = None;
+        while self.peek().as_deref() != Some("}") {
+            if self.accept(";")? {
+                continue;
+            }
+            if self.accept("FILL")? {
+                self.expect("(")?;
+                fill = Some(self.parse_fill()?);
+                self.expect(")")?;
+                continue;
+            }
+            input_sections.push(self.parse_input_section(false)?);
+        }
+        self.expect("}")?;
+        Ok((input_sections, fill))
+    }
+
+    /// Fill value of FILL(value) or `= value`, only one byte is supported
+    fn parse_fill(&mut self) -> anyhow::Result<u8> {
+        let value = self.parse_expression()?.evaluate(&NoLayout)?;
+        u8::try_from(value).map_err(|_| anyhow!("Fill value {:#x} is larger than a byte", value))
+    }
+
+    /// `OVERLAY : [NOCROSSREFS] { name { ... } ... } [AT> region]`, also
+    /// accepts `OVERLAY AT> region { ... }`
+    fn parse_overlay(&mut self, script: &mut Script) -> anyhow::Result<()> {
+        let overlay = script
+            .sections
+            .iter()
+            .filter_map(|output_section| output_section.overlay)
+            .max()
+            .map_or(0, |index| index + 1);
+        let mut load_region = None;
+        loop {
+            match self.peek().as_deref() {
+                Some("{") => break,
+                Some(":") | Some("NOCROSSREFS") => {
+                    self.next()?;
+                }
+                Some("AT") => {
+                    self.next()?;
+                    self.expect(">")?;
+                    load_region = Some(self.next()?);
+                }
+                // the address is chosen by layout
+                _ => bail!("Unsupported {} in OVERLAY", self.next()?),
+            }
+        }
+
+        self.expect("{")?;
+        let first = script.sections.len();
+        loop {
+            let name = self.next()?;
+            if name == "}" {
+                break;
+            }
+            self.accept(":")?;
+            let (input_sections, mut fill) = self.parse_section_contents()?;
+            if self.accept("=")? {
+                fill = Some(self.parse_fill()?);
+            }
+            script.sections.push(OutputSectionDescription {
+                name,
+                input_sections,
+                region: None,
+                load_region: None,
+                load_address: None,
+                overlay: Some(overlay),
+                fill,
+                align: None,
+            });
+        }
+        if self.accept("AT")? {
+            self.expect(">")?;
+            load_region = Some(self.next()?);
+        }
+        for output_section in &mut script.sections[first..] {
+            output_section.load_region = load_region.clone();
+            // where to copy the overlay from, named without the dot
+            let name = &output_section.name;
+            let suffix: String = name
+                .chars()
+                .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+                .collect();
+            let start = Expression::LoadAddr(name.clone());
+            let stop = Expression::Binary(
+                Box::new(start.clone()),
+                BinaryOperator::Add,
+                Box::new(Expression::SizeOf(name.clone())),
+            );
+            for (prefix, expression) in [("__load_start_", start), ("__load_stop_", stop)] {
+                script.assignments.push(SymbolAssignment {
+                    name: format!("{}{}", prefix, suffix),
+                    expression,
+                    hidden: false,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// `ORIGIN = value` or `LENGTH = value`, with abbreviated keywords
+    fn parse_memory_attribute(&mut self, keywords: &[&str]) -> anyhow::Result<u64> {
+        let keyword = self.next()?;
+        if !keywords.contains(&keyword.as_str()) {
+            bail!(
+                "Expected {} in linker script, found {}",
+                keywords[0],
+                keyword
+            );
+        }
+        self.expect("=")?;
+        self.parse_expression()?.evaluate(&NoLayout)
+    }
+
+    fn parse_memory(&mut self) -> anyhow::Result<Vec<MemoryRegion>> {
+        self.expect("{")?;
+        let mut memory = vec![];
+        loop {
+            let name = self.next()?;
+            if name == "}" {
+                break;
+            }
+            let attributes = if self.peek().as_deref() == Some("(") {
+                self.parse_argument()?
+            } else {
+                String::new()
+            };
+            self.expect(":")?;
+            let origin = self.parse_memory_attribute(&["ORIGIN", "org", "o"])?;
+            self.accept(",")?;
+            let length = self.parse_memory_attribute(&["LENGTH", "len", "l"])?;
+            memory.push(MemoryRegion {
+                name,
+                attributes,
+                origin,
+                length,
+            });
+        }
+        Ok(memory)
+    }
+}
+
+/// Parse linker script
+pub fn parse_script(text: &str) -> anyhow::Result<Script> {
+    let mut parser = Parser {
+        text: text.to_string(),
+        offset: 0,
+    };
+    let mut script = Script::default();
+    while parser.peek().is_some() {
+        let command = parser.next()?;
+        match command.as_str() {
+            "ENTRY" => {
+                script.entry = Some(parser.parse_argument()?);
+            }
+            "OUTPUT" => {
+                script.output = Some(parser.parse_argument()?);
+            }
+            "OUTPUT_FORMAT" => {
+                script.output_formats = parser.parse_arguments()?;
+                if ![1, 3].contains(&script.output_formats.len()) {
+                    bail!("OUTPUT_FORMAT takes one or three formats");
+                }
+            }
+            "SEARCH_DIR" => {
+                script.search_dirs.push(parser.parse_argument()?);
+            }
+            "INPUT" => {
+                script.inputs.extend(parser.parse_arguments()?);
+            }
+            "GROUP" => {
+                script.inputs.push("-(".to_string());
+                script.inputs.extend(parser.parse_arguments()?);
+                script.inputs.push("-)".to_string());
+            }
+            "SECTIONS" => {
+                parser.parse_sections(&mut script)?;
+            }
+            "PROVIDE" | "PROVIDE_HIDDEN" => {
+                let hidden = command == "PROVIDE_HIDDEN";
+                script.assignments.push(parser.parse_provide(hidden)?);
+            }
+            "ASSERT" => {
+                script.assertions.push(parser.parse_assert()?);
+            }
+            "MEMORY" => {
+                script.memory.extend(parser.parse_memory()?);
+            }
+            ";" => {}
+            _ => bail!("Unsupported linker script command {}", command),
+        }
+    }
+    for output_section in &script.sections {
+        for region in output_section
+            .region
+            .iter()
+            .chain(output_section.load_region.iter())
+        {
+            if script.memory_region(region).is_none() {
+                bail!("Memory region {} is not declared", region);
+            }
+        }
+    }
+    Ok(script)
+}
+
+/// Parse a standalone symbol value of --defsym, returns the expression and
+/// whether it is wrapped in HIDDEN(...)
+pub fn parse_symbol_value(text: &str) -> anyhow::Result<(Expression, bool)> {
+    let mut parser = Parser {
+        text: text.to_string(),
+        offset: 0,
+    };
+    let value = parser.parse_symbol_value()?;
+    if let Some(token) = parser.peek_expression() {
+        bail!("Unexpected {} after expression {}", token, text);
+    }
+    Ok(value)
+}
+
+/// Read and parse linker script from file
+pub fn read_script(path: &str) -> anyhow::Result<Script> {
+    let text = std::fs::read_to_string(path).context(format!("Reading linker script {}", path))?;
+    let mut script = parse_script(&text).context(format!("Parsing linker script {}", path))?;
+    // relative files missing in the current directory are found next to
+    // the script
+    let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+    for input in &mut script.inputs {
+        if input.starts_with('-') || Path::new(input).exists() {
+            continue;
+        }
+        let candidate = dir.join(&*input);
+        if Path::new(input).is_relative() && candidate.exists() {
+            *input = candidate.to_string_lossy().to_string();
+        }
+    }
+    Ok(script)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_parse_script() {
+        let script = parse_script(
+            r#"
+            /* firmware layout */
+            ENTRY(reset)
+            OUTPUT("firmware.elf")
+            SEARCH_DIR(lib)
+            INPUT(start.o, -lc)
+            SECTIONS
+            {
+                .text : { KEEP(*(.vectors)) *(.text .text.*) }
+                .data : { data.o }
+                /DISCARD/ : { *(.comment) }
+            }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(script.entry.as_deref(), Some("reset"));
+        assert_eq!(script.output.as_deref(), Some("firmware.elf"));
+        assert_eq!(script.search_dirs, vec!["lib"]);
+        assert_eq!(script.inputs, vec!["start.o", "-lc"]);
+        assert_eq!(script.sections.len(), 3);
+        assert!(script.sections[0].input_sections[0].keep);
+        assert!(script.keeps("a.o", ".vectors"));
+        assert!(!script.keeps("a.o", ".text"));
+
+        assert_eq!(script.output_section_name("a.o", ".vectors"), Some(".text"));
+        assert_eq!(
+            script.output_section_name("a.o", ".text.foo"),
+            Some(".text")
+        );
+        assert_eq!(script.output_section_name("data.o", ".bss"), Some(".data"));
+        assert_eq!(script.output_section_name("a.o", ".comment"), Some(DISCARD));
+        assert_eq!(script.output_section_name("a.o", ".rodata"), None);
+        assert_eq!(script.section_order(".data"), Some(1));
+
+        assert!(parse_script("SECTIONS { .text : { *(.text) }").is_err());
+        assert!(parse_script("SECTIONS { .text : { *(.text) } > ROM }").is_err());
+        assert!(parse_script("VERSION { }").is_err());
+    }
+
+    #[test]
+    fn test_parse_memory() {
+        let script = parse_script(
+            "MEMORY
+            {
+                FLASH (rx) : ORIGIN = 0x08000000, LENGTH = 512K
+                RAM (rwx) : org = 0x20000000, l = 0x20000
+            }
+            SECTIONS
+            {
+                .text : { *(.text*) } > FLASH
+                .data : { *(.data*) } >RAM
+            }",
+        )
+        .unwrap();
+        let flash = script.memory_region("FLASH").unwrap();
+        assert_eq!(flash.attributes, "rx");
+        assert_eq!(flash.origin, 0x08000000);
+        assert_eq!(flash.length, 512 * 1024);
+        let ram = script.memory_region("RAM").unwrap();
+        assert_eq!(ram.origin, 0x20000000);
+        assert_eq!(ram.length, 0x20000);
+        assert_eq!(script.sections[0].region.as_deref(), Some("FLASH"));
+        assert_eq!(script.sections[1].region.as_deref(), Some("RAM"));
+    }
+
+    #[test]
+    fn test_output_format() {
+        let script = parse_script(
+            r#"OUTPUT_FORMAT("elf64-x86-64", "elf64-bigaarch64", "elf64-littleaarch64")"#,
+        )
+        .unwrap();
+        let format = |endianness| {
+            let opt = Opt {
+                endianness,
+                ..Opt::default()
+            };
+            script.apply(&opt).output_format.unwrap()
+        };
+        assert_eq!(format(None), "elf64-x86-64");
+        assert_eq!(format(Some(Endianness::Big)), "elf64-bigaarch64");
+        assert_eq!(format(Some(Endianness::Little)), "elf64-littleaarch64");
+        let script = parse_script("OUTPUT_FORMAT(elf64-x86-64)").unwrap();
+        let opt = Opt {
+            endianness: Some(Endianness::Big),
+            ..Opt::default()
+        };
+        assert_eq!(
+            script.apply(&opt).output_format.as_deref(),
+            Some("elf64-x86-64")
+        );
+        assert!(parse_script("OUTPUT_FORMAT(a, b)").is_err());
+    }
+
+    #[test]
+    fn test_entry() {
+        // the last ENTRY wins
+        let script = parse_script("ENTRY(first) ENTRY(second)").unwrap();
+        assert_eq!(script.entry.as_deref(), Some("second"));
+        // -e and an entry address take precedence
+        let entry = |opt: Opt| script.apply(&opt).entry_symbol;
+        assert_eq!(entry(Opt::default()).as_deref(), Some("second"));
+        let opt = Opt {
+            entry_symbol: Some("main".to_string()),
+            ..Opt::default()
+        };
+        assert_eq!(entry(opt).as_deref(), Some("main"));
+        let opt = Opt {
+            entry_address: Some(0x401000),
+            ..Opt::default()
+        };
+        assert_eq!(entry(opt), None);
+        // without ENTRY either, the linker picks _start or .text
+        assert_eq!(Script::default().apply(&Opt::default()).entry_symbol, None);
+    }
+
+    #[test]
+    fn test_output() {
+        let script = parse_script("OUTPUT(myout)").unwrap();
+        let output = |output: Option<&str>| {
+            let opt = Opt {
+                output: output.map(|output| output.to_string()),
+                ..Opt::default()
+            };
+            script.apply(&opt).output.unwrap()
+        };
+        assert_eq!(output(None), "myout");
+        assert_eq!(output(Some("hello")), "hello");
+        let opt = Script::default().apply(&Opt::default());
+        assert_eq!(opt.output.as_deref(), Some("a.out"));
+    }
+
+    #[test]
+    fn test_search_dir() {
+        let script = parse_script(r#"SEARCH_DIR("/usr/lib") SEARCH_DIR(/lib)"#).unwrap();
+        let opt = Opt {
+            search_dir: vec!["lib".to_string()],
+            ..Opt::default()
+        };
+        assert_eq!(
+            script.apply(&opt).search_dir,
+            vec!["/usr/lib", "/lib", "lib"]
+        );
+    }
+
+    #[test]
+    fn test_parse_group() {
+        let script = parse_script("INPUT(a.o) GROUP(libc.a -lm)").unwrap();
+        assert_eq!(script.inputs, vec!["a.o", "-(", "libc.a", "-lm", "-)"]);
+        let opt = script.apply(&Opt::default());
+        assert!(matches!(
+            opt.obj_file.as_slice(),
+            [
+                ObjectFileOpt::File(_),
+                ObjectFileOpt::StartGroup,
+                ObjectFileOpt::File(_),
+                ObjectFileOpt::Library(_),
+                ObjectFileOpt::EndGroup
+            ]
+        ));
+    }
+
+    #[test]
+    fn test_input_section_patterns() {
+        let script = parse_script(
+            "SECTIONS
+            {
+                .fast : { fast?.o(.text .text.*) }
+                .text : { *(.text .text.*) }
+            }",
+        )
+        .unwrap();
+        assert_eq!(
+            script.output_section_name("fast1.o", ".text"),
+            Some(".fast")
+        );
+        assert_eq!(
+            script.output_section_name("fast2.o", ".text.hot"),
+            Some(".fast")
+        );
+        assert_eq!(script.output_section_name("fast.o", ".text"), Some(".text"));
+        assert_eq!(script.output_section_name("fast1.o", ".texts"), None);
+    }
+
+    #[test]
+    fn test_parse_sort() {
+        let script = parse_script(
+            "SECTIONS
+            {
+                .text : { *(SORT_BY_ALIGNMENT(.text.*)) *(.text) }
+                .rodata : { KEEP(*(SORT(.rodata.*) .rodata)) }
+                .data : { SORT(*(SORT_BY_ALIGNMENT(.data.*))) }
+            }",
+        )
+        .unwrap();
+        assert_eq!(
+            script.input_section_sort("a.o", ".text.foo"),
+            Some(SortOrder::Alignment)
+        );
+        assert_eq!(script.input_section_sort("a.o", ".text"), None);
+        assert_eq!(
+            script.sections[1].input_sections[0].section_patterns,
+            vec![".rodata.*", ".rodata"]
+        );
+        assert_eq!(
+            script.input_section_sort("a.o", ".rodata"),
+            Some(SortOrder::Name)
+        );
+        assert_eq!(
+            script.input_section_sort("a.o", ".data.bar"),
+            Some(SortOrder::Name)
+        );
+        assert_eq!(
+            script.output_section_name("a.o", ".data.bar"),
+            Some(".data")
+        );
+    }
+
+    #[test]
+    fn test_parse_provide() {
+        let script = parse_script(
+            "PROVIDE(__stack = 0x20008000);
+            SECTIONS
+            {
+                .init_array : { *(.init_array) }
+                PROVIDE_HIDDEN(__init_array_start = __init);
+            }",
+        )
+        .unwrap();
+        assert_eq!(script.assignments.len(), 2);
+        assert_eq!(script.assignments[0].name, "__stack");
+        assert_eq!(
+            script.assignments[0].expression,
+            Expression::Number(0x20008000)
+        );
+        assert!(!script.assignments[0].hidden);
+        assert_eq!(script.assignments[1].name, "__init_array_start");
+        assert_eq!(
+            script.assignments[1].expression,
+            Expression::Symbol("__init".to_string())
+        );
+        assert!(script.assignments[1].hidden);
+        assert_eq!(script.sections.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_hidden() {
+        let script = parse_script("PROVIDE(__end = HIDDEN(_end + 16))").unwrap();
+        assert!(script.assignments[0].hidden);
+        assert_eq!(
+            script.assignments[0].expression,
+            Expression::Binary(
+                Box::new(Expression::Symbol("_end".to_string())),
+                BinaryOperator::Add,
+                Box::new(Expression::Number(16))
+            )
+        );
+        assert_eq!(
+            parse_symbol_value("HIDDEN(hello)").unwrap(),
+            (Expression::Symbol("hello".to_string()), true)
+        );
+        assert!(!parse_symbol_value("hello + 1").unwrap().1);
+        assert!(parse_symbol_value("HIDDEN(hello").is_err());
+        assert!(parse_symbol_value("HIDDEN(hello) + 1").is_err());
+    }
+
+    #[test]
+    fn test_parse_overlay() {
+        let script = parse_script(
+            "MEMORY { flash (rx) : ORIGIN = 0, LENGTH = 1M }
+            SECTIONS
+            {
+                .text : { *(.text) }
+                OVERLAY : NOCROSSREFS
+                {
+                    .overlay0 { *(.overlay0) }
+                    .overlay1 { *(.overlay1) }
+                } AT> flash
+                OVERLAY AT> flash { .overlay2 { *(.overlay2) } }
+            }",
+        )
+        .unwrap();
+        assert_eq!(script.sections.len(), 4);
+        assert_eq!(script.overlay(".text"), None);
+        assert_eq!(script.overlay(".overlay0"), Some(0));
+        assert_eq!(script.overlay(".overlay1"), Some(0));
+        assert_eq!(script.overlay(".overlay2"), Some(1));
+        assert_eq!(script.sections[2].load_region.as_deref(), Some("flash"));
+        assert_eq!(script.assignments[2].name, "__load_start_overlay1");
+        assert_eq!(script.assignments[3].name, "__load_stop_overlay1");
+        assert_eq!(
+            script.output_section_name("a.o", ".overlay1"),
+            Some(".overlay1")
+        );
+        assert!(parse_script("SECTIONS { OVERLAY 0x1000 : { .a { *(.a) } } }").is_err());
+    }
+
+    #[test]
+    fn test_parse_fill() {
+        let script = parse_script(
+            "SECTIONS
+            {
+                .text : { FILL(0x90) *(.text) }
+                .rodata : { *(.rodata) } = 0xff
+                .data : { *(.data) }
+            }",
+        )
+        .unwrap();
+        assert_eq!(script.fill(".text"), Some(0x90));
+        assert_eq!(script.fill(".rodata"), Some(0xff));
+        assert_eq!(script.fill(".data"), None);
+        assert!(parse_script("SECTIONS { .text : { *(.text) } = 0x9090 }").is_err());
+    }
+
+    #[test]
+    fn test_parse_load_address() {
+        let script = parse_script(
+            "MEMORY
+            {
+                rom (rx) : ORIGIN = 0x400000, LENGTH = 1M
+                ram (rw) : ORIGIN = 0x800000, LENGTH = 1M
+            }
+            SECTIONS
+            {
+                .text : { *(.text) } > rom
+                .data : AT(0x500000 + 0x100) { *(.data) } > ram AT> rom = 0
+                .bss : { *(.bss) } > ram
+            }",
+        )
+        .unwrap();
+        assert_eq!(
+            script
+                .load_address(".data")
+                .unwrap()
+                .evaluate(&NoLayout)
+                .unwrap(),
+            0x500100
+        );
+        assert!(script.load_address(".text").is_none());
+        assert_eq!(script.sections[1].region.as_deref(), Some("ram"));
+        assert_eq!(script.sections[1].load_region.as_deref(), Some("rom"));
+        assert_eq!(script.fill(".data"), Some(0));
+        assert!(parse_script("SECTIONS { .data : { *(.data) } AT> rom }").is_err());
+    }
+
+    #[test]
+    fn test_parse_location_assignment() {
+        let script = parse_script(
+            "SECTIONS
+            {
+                . = 0x8000000;
+                .text : { *(.text) }
+                .=ALIGN(0x1000);
+                . += 0x100;
+                .data : { *(.data) }
+            }",
+        )
+        .unwrap();
+        let assignments = &script.location_assignments;
+        assert_eq!(assignments.len(), 3);
+        assert_eq!(assignments[0].expression, Expression::Number(0x8000000));
+        assert_eq!(assignments[0].section, 0);
+        assert_eq!(
+            assignments[1].expression,
+            Expression::Align(
+                Box::new(Expression::Location),
+                Box::new(Expression::Number(0x1000))
+            )
+        );
+        assert_eq!(
+            assignments[2].expression,
+            Expression::Binary(
+                Box::new(Expression::Location),
+                BinaryOperator::Add,
+                Box::new(Expression::Number(0x100))
+            )
+        );
+        assert_eq!(assignments[2].section, 1);
+        assert_eq!(script.sections.len(), 2);
+        assert!(parse_script("SECTIONS { . 0x1000; }").is_err());
+    }
+}
@@ -0,0 +1,405 @@
+use super::{parse_number, Parser};
+use anyhow::{anyhow, bail};
+
+/// Unary operators in expressions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOperator {
+    Negate,
+    Not,
+    Complement,
+}
+
+/// Binary operators in expressions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOperator {
+    Or,
+    And,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    ShiftLeft,
+    ShiftRight,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+}
+
+/// Binary operators from the lowest to the highest precedence, like C
+const BINARY_OPERATORS: &[&[(&str, BinaryOperator)]] = &[
+    &[("||", BinaryOperator::Or)],
+    &[("&&", BinaryOperator::And)],
+    &[("|", BinaryOperator::BitOr)],
+    &[("^", BinaryOperator::BitXor)],
+    &[("&", BinaryOperator::BitAnd)],
+    &[
+        ("==", BinaryOperator::Equal),
+        ("!=", BinaryOperator::NotEqual),
+    ],
+    &[
+        ("<", BinaryOperator::Less),
+        ("<=", BinaryOperator::LessEqual),
+        (">", BinaryOperator::Greater),
+        (">=", BinaryOperator::GreaterEqual),
+    ],
+    &[
+        ("<<", BinaryOperator::ShiftLeft),
+        (">>", BinaryOperator::ShiftRight),
+    ],
+    &[("+", BinaryOperator::Add), ("-", BinaryOperator::Subtract)],
+    &[
+        ("*", BinaryOperator::Multiply),
+        ("/", BinaryOperator::Divide),
+        ("%", BinaryOperator::Remainder),
+    ],
+];
+
+/// Expression in linker script
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expression {
+    /// absolute address
+    Number(u64),
+    /// address of a symbol
+    Symbol(String),
+    /// SIZEOF(section)
+    SizeOf(String),
+    /// ADDR(section)
+    Addr(String),
+    /// LOADADDR(section)
+    LoadAddr(String),
+    /// SIZEOF_HEADERS: ELF header and program headers
+    SizeOfHeaders,
+    /// `.`: the location counter
+    Location,
+    /// ALIGN(expression, align): rounded up to a multiple of align
+    Align(Box<Expression>, Box<Expression>),
+    /// MAX(expression, expression)
+    Max(Box<Expression>, Box<Expression>),
+    /// MIN(expression, expression)
+    Min(Box<Expression>, Box<Expression>),
+    /// LOG2CEIL(expression): exponent of the nearest power of two not below
+    Log2Ceil(Box<Expression>),
+    Unary(UnaryOperator, Box<Expression>),
+    Binary(Box<Expression>, BinaryOperator, Box<Expression>),
+    /// condition ? value : value
+    Conditional(Box<Expression>, Box<Expression>, Box<Expression>),
+}
+
+/// Symbols and sections of the output, expressions are evaluated against it
+pub trait Environment {
+    fn symbol_address(&self, name: &str) -> Option<u64>;
+    fn section_address(&self, name: &str) -> Option<u64>;
+    fn section_load_address(&self, name: &str) -> Option<u64>;
+    fn section_size(&self, name: &str) -> Option<u64>;
+    fn headers_size(&self) -> Option<u64>;
+    fn location_counter(&self) -> Option<u64>;
+}
+
+/// Environment before layout: only constant expressions can be evaluated
+pub struct NoLayout;
+
+impl Environment for NoLayout {
+    fn symbol_address(&self, _name: &str) -> Option<u64> {
+        None
+    }
+
+    fn section_address(&self, _name: &str) -> Option<u64> {
+        None
+    }
+
+    fn section_load_address(&self, _name: &str) -> Option<u64> {
+        None
+    }
+
+    fn section_size(&self, _name: &str) -> Option<u64> {
+        None
+    }
+
+    fn headers_size(&self) -> Option<u64> {
+        None
+    }
+
+    fn location_counter(&self) -> Option<u64> {
+        None
+    }
+}
+
+impl Expression {
+    /// Compute the value, wrapping around on overflow like GNU ld
+    pub fn evaluate(&self, environment: &dyn Environment) -> anyhow::Result<u64> {
+        let section = |value: Option<u64>, name: &str| {
+            value.ok_or(anyhow!("Undefined section {} in linker script", name))
+        };
+        Ok(match self {
+            Expression::Number(value) => *value,
+            Expression::Symbol(name) => environment
+                .symbol_address(name)
+                .ok_or(anyhow!("Undefined symbol {} in linker script", name))?,
+            Expression::SizeOf(name) => section(environment.section_size(name), name)?,
+            Expression::Addr(name) => section(environment.section_address(name), name)?,
+            Expression::LoadAddr(name) => section(environment.section_load_address(name), name)?,
+            Expression::SizeOfHeaders => environment
+                .headers_size()
+                .ok_or(anyhow!("SIZEOF_HEADERS is unknown before layout"))?,
+            Expression::Location => environment.location_counter().ok_or(anyhow!(
+                "Location counter . is only known in assignments to . in SECTIONS"
+            ))?,
+            Expression::Align(value, align) => {
+                let value = value.evaluate(environment)?;
+                match align.evaluate(environment)? {
+                    0 => bail!("Alignment 0 in ALIGN of linker script"),
+                    align => value.wrapping_add(align - 1) / align * align,
+                }
+            }
+            Expression::Max(left, right) => left
+                .evaluate(environment)?
+                .max(right.evaluate(environment)?),
+            Expression::Min(left, right) => left
+                .evaluate(environment)?
+                .min(right.evaluate(environment)?),
+            Expression::Log2Ceil(value) => match value.evaluate(environment)? {
+                // LOG2CEIL(0) is 0 like GNU ld
+                0 | 1 => 0,
+                value => (u64::BITS - (value - 1).leading_zeros()) as u64,
+            },
+            Expression::Unary(operator, operand) => {
+                let value = operand.evaluate(environment)?;
+                match operator {
+                    UnaryOperator::Negate => value.wrapping_neg(),
+                    UnaryOperator::Not => (value == 0) as u64,
+                    UnaryOperator::Complement => !value,
+                }
+            }
+            Expression::Binary(left, operator, right) => {
+                let left = left.evaluate(environment)?;
+                let right = right.evaluate(environment)?;
+                match operator {
+                    BinaryOperator::Or => (left != 0 || right != 0) as u64,
+                    BinaryOperator::And => (left != 0 && right != 0) as u64,
+                    BinaryOperator::BitOr => left | right,
+                    BinaryOperator::BitXor => left ^ right,
+                    BinaryOperator::BitAnd => left & right,
+                    BinaryOperator::Equal => (left == right) as u64,
+                    BinaryOperator::NotEqual => (left != right) as u64,
+                    BinaryOperator::Less => (left < right) as u64,
+                    BinaryOperator::LessEqual => (left <= right) as u64,
+                    BinaryOperator::Greater => (left > right) as u64,
+                    BinaryOperator::GreaterEqual => (left >= right) as u64,
+                    BinaryOperator::ShiftLeft => left.wrapping_shl(right as u32),
+                    BinaryOperator::ShiftRight => left.wrapping_shr(right as u32),
+                    BinaryOperator::Add => left.wrapping_add(right),
+                    BinaryOperator::Subtract => left.wrapping_sub(right),
+                    BinaryOperator::Multiply => left.wrapping_mul(right),
+                    BinaryOperator::Divide | BinaryOperator::Remainder if right == 0 => {
+                        bail!("Division by zero in linker script")
+                    }
+                    BinaryOperator::Divide => left / right,
+                    BinaryOperator::Remainder => left % right,
+                }
+            }
+            Expression::Conditional(condition, then, otherwise) => {
+                if condition.evaluate(environment)? != 0 {
+                    then.evaluate(environment)?
+                } else {
+                    otherwise.evaluate(environment)?
+                }
+            }
+        })
+    }
+}
+
+impl Parser {
+    /// Parse an expression, tokens are split by operators here
+    pub(super) fn parse_expression(&mut self) -> anyhow::Result<Expression> {
+        let condition = self.parse_binary(0)?;
+        if self.peek_expression().as_deref() != Some("?") {
+            return Ok(condition);
+        }
+        self.next_expression()?;
+        let then = self.parse_expression()?;
+        self.expect_expression(":")?;
+        let otherwise = self.parse_expression()?;
+        Ok(Expression::Conditional(
+            Box::new(condition),
+            Box::new(then),
+            Box::new(otherwise),
+        ))
+    }
+
+    /// Parse binary operators with at least the given precedence
+    fn parse_binary(&mut self, precedence: usize) -> anyhow::Result<Expression> {
+        let Some(operators) = BINARY_OPERATORS.get(precedence) else {
+            return self.parse_unary();
+        };
+        let mut left = self.parse_binary(precedence + 1)?;
+        loop {
+            let token = self.peek_expression();
+            let Some((_, operator)) = operators
+                .iter()
+                .find(|(symbol, _)| Some(*symbol) == token.as_deref())
+            else {
+                return Ok(left);
+            };
+            self.next_expression()?;
+            let right = self.parse_binary(precedence + 1)?;
+            left = Expression::Binary(Box::new(left), *operator, Box::new(right));
+        }
+    }
+
+    fn parse_unary(&mut self) -> anyhow::Result<Expression> {
+        let token = self.next_expression()?;
+        let operator = match token.as_str() {
+            "-" => UnaryOperator::Negate,
+            "!" => UnaryOperator::Not,
+            "~" => UnaryOperator::Complement,
+            "(" => {
+                let expression = self.parse_expression()?;
+                self.expect_expression(")")?;
+                return Ok(expression);
+            }
+            _ => return self.parse_primary(token),
+        };
+        Ok(Expression::Unary(operator, Box::new(self.parse_unary()?)))
+    }
+
+    fn parse_primary(&mut self, token: String) -> anyhow::Result<Expression> {
+        if token.starts_with(|c: char| c.is_ascii_digit()) {
+            return Ok(Expression::Number(parse_number(&token)?));
+        }
+        if token == "SIZEOF_HEADERS" {
+            return Ok(Expression::SizeOfHeaders);
+        }
+        if token == "." {
+            return Ok(Expression::Location);
+        }
+        if token == "ALIGN" {
+            self.expect_expression("(")?;
+            let mut value = self.parse_expression()?;
+            // ALIGN(align) rounds up the location counter
+            let align = if self.peek_expression().as_deref() == Some(",") {
+                self.next_expression()?;
+                self.parse_expression()?
+            } else {
+                std::mem::replace(&mut value, Expression::Location)
+            };
+            self.expect_expression(")")?;
+            return Ok(Expression::Align(Box::new(value), Box::new(align)));
+        }
+        if token == "MAX" || token == "MIN" {
+            self.expect_expression("(")?;
+            let left = Box::new(self.parse_expression()?);
+            self.expect_expression(",")?;
+            let right = Box::new(self.parse_expression()?);
+            self.expect_expression(")")?;
+            return Ok(if token == "MAX" {
+                Expression::Max(left, right)
+            } else {
+                Expression::Min(left, right)
+            });
+        }
+        if token == "LOG2CEIL" {
+            self.expect_expression("(")?;
+            let value = self.parse_expression()?;
+            self.expect_expression(")")?;
+            return Ok(Expression::Log2Ceil(Box::new(value)));
+        }
+        let function: Option<fn(String) -> Expression> = match token.as_str() {
+            "SIZEOF" => Some(Expression::SizeOf),
+            "ADDR" => Some(Expression::Addr),
+            "LOADADDR" => Some(Expression::LoadAddr),
+            _ => None,
+        };
+        match function {
+            Some(function) => {
+                self.expect_expression("(")?;
+                let section = self.next_expression()?;
+                self.expect_expression(")")?;
+                Ok(function(section))
+            }
+            None if token.starts_with(|c: char| c.is_alphanumeric() || "_.$".contains(c)) => {
+                Ok(Expression::Symbol(token))
+            }
+            None => bail!("Unexpected {} in linker script expression", token),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script::parse_script;
+
+    struct Layout;
+
+    impl Environment for Layout {
+        fn symbol_address(&self, name: &str) -> Option<u64> {
+            (name == "_start").then_some(0x401000)
+        }
+
+        fn section_address(&self, name: &str) -> Option<u64> {
+            (name == ".text").then_some(0x401000)
+        }
+
+        fn section_load_address(&self, name: &str) -> Option<u64> {
+            self.section_address(name)
+        }
+
+        fn section_size(&self, name: &str) -> Option<u64> {
+            (name == ".text").then_some(0x100)
+        }
+
+        fn headers_size(&self) -> Option<u64> {
+            Some(0x40 + 4 * 0x38)
+        }
+
+        fn location_counter(&self) -> Option<u64> {
+            None
+        }
+    }
+
+    fn evaluate(expression: &str) -> anyhow::Result<u64> {
+        let script = parse_script(&format!("ASSERT({}, \"message\")", expression))?;
+        script.assertions[0].condition.evaluate(&Layout)
+    }
+
+    #[test]
+    fn test_evaluate() {
+        assert_eq!(evaluate("1 + 2 * 3").unwrap(), 7);
+        assert_eq!(evaluate("(1 + 2) * 3").unwrap(), 9);
+        assert_eq!(evaluate("10 - 4 - 3").unwrap(), 3);
+        assert_eq!(evaluate("4K / 2 % 3").unwrap(), 2);
+        assert_eq!(evaluate("1 << 4 | 1").unwrap(), 17);
+        assert_eq!(evaluate("!0 && ~0 == -1").unwrap(), 1);
+        assert_eq!(evaluate("SIZEOF(.text) < 0x80000").unwrap(), 1);
+        assert_eq!(evaluate("ADDR(.text) + SIZEOF(.text)").unwrap(), 0x401100);
+        assert_eq!(evaluate("LOADADDR(.text) == _start").unwrap(), 1);
+        assert_eq!(evaluate("SIZEOF(.text) > 16 ? 1 : 2").unwrap(), 1);
+        assert_eq!(evaluate("0x400000 + SIZEOF_HEADERS").unwrap(), 0x400120);
+        assert_eq!(evaluate("ALIGN(_start + 1, 0x1000)").unwrap(), 0x402000);
+        assert_eq!(evaluate("ALIGN(0x400000, 4K)").unwrap(), 0x400000);
+        assert_eq!(evaluate("ALIGN(13, 3 * 2)").unwrap(), 18);
+        assert!(evaluate("ALIGN(1, 0)").is_err());
+        assert_eq!(evaluate("MAX(SIZEOF(.text), 0x80)").unwrap(), 0x100);
+        assert_eq!(evaluate("MIN(SIZEOF(.text), 0x80) + 1").unwrap(), 0x81);
+        assert_eq!(evaluate("MAX(1, MIN(3, 2))").unwrap(), 2);
+        assert_eq!(evaluate("LOG2CEIL(0)").unwrap(), 0);
+        assert_eq!(evaluate("LOG2CEIL(1)").unwrap(), 0);
+        assert_eq!(evaluate("LOG2CEIL(2)").unwrap(), 1);
+        assert_eq!(evaluate("LOG2CEIL(SIZEOF(.text) + 1)").unwrap(), 9);
+        assert_eq!(evaluate("1 << LOG2CEIL(0x3000)").unwrap(), 0x4000);
+        assert!(evaluate("MAX(1)").is_err());
+        assert!(evaluate("ALIGN(16)").is_err());
+        assert!(evaluate(". + 1").is_err());
+        assert!(evaluate("SIZEOF(.data)").is_err());
+        assert!(evaluate("undefined").is_err());
+        assert!(evaluate("1 / 0").is_err());
+        assert!(evaluate("1 +").is_err());
+    }
+}
@@ -0,0 +1,21 @@
+    # 32-bit immediates are zero extended, R_X86_64_32
+    .section .text
+    .globl _start
+_start:
+    movl    $far, %eax
+
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    movl    $hello, %esi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
+
+    .section .rodata
+hello:
+    .string "Hello world!\n"
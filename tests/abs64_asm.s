@@ -0,0 +1,32 @@
+    # .rodata holds a pointer to itself, R_X86_64_64
+    .section .text
+    .globl _start
+_start:
+    mov     self(%rip), %rax
+    lea     self(%rip), %rcx
+    cmp     %rax, %rcx
+    jne     fail
+
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    lea     hello(%rip), %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
+
+fail:
+    # _exit(1)
+    mov     $1, %rdi
+    mov     $60, %rax
+    syscall
+
+    .section .rodata
+self:
+    .quad   self
+hello:
+    .string "Hello world!\n"
@@ -0,0 +1,11 @@
+    # message is defined by another member of the archive
+    .section .text
+    .globl hello
+hello:
+    # write(1, message, 13)
+    mov     $1, %rdi
+    lea     message(%rip), %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+    ret
@@ -0,0 +1,10 @@
+    # hello is defined in libarchive_asm.a
+    .section .text
+    .globl _start
+_start:
+    call    hello
+
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
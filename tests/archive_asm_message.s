@@ -0,0 +1,4 @@
+    .section .rodata
+    .globl message
+message:
+    .string "Hello world!\n"
@@ -0,0 +1,6 @@
+    # not extracted: nothing references unused, so missing is not needed
+    .section .text
+    .globl unused
+unused:
+    call    missing
+    ret
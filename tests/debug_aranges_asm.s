@@ -0,0 +1,35 @@
+    .section .text
+    .globl _start
+_start:
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
+_end:
+
+    # a set of unsupported version, then one covering .text
+    .section .debug_aranges,"",@progbits
+    .long   2f - 1f
+1:
+    .short  3
+    .long   0
+    .byte   8
+    .byte   0
+    .long   0
+    .quad   _start
+    .quad   _end - _start
+    .quad   0
+    .quad   0
+2:
+    .long   4f - 3f
+3:
+    .short  2
+    .long   0
+    .byte   8
+    .byte   0
+    .long   0
+    .quad   _start
+    .quad   _end - _start
+    .quad   0
+    .quad   0
+4:
@@ -0,0 +1,18 @@
+    # entry is foo::bar() in C++
+    .section .text
+    .globl _ZN3foo3barEv
+_ZN3foo3barEv:
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    lea     hello(%rip), %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
+
+hello:
+    .string "Hello world!\n"
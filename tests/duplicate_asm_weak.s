@@ -0,0 +1,4 @@
+    .section .rodata
+    .weak message
+message:
+    .string "Weak world!!\n"
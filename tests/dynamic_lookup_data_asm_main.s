@@ -0,0 +1,15 @@
+    # message is defined by a library loaded at runtime
+    .section .text
+    .globl _start
+_start:
+    # write(1, message, 13), address loaded from GOT
+    mov     $1, %rdi
+    movq    message@GOTPCREL(%rip), %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
@@ -0,0 +1,10 @@
+    .section .text
+    .globl _start
+_start:
+    .cfi_startproc
+    call    print
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
+    .cfi_endproc
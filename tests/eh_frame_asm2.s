@@ -0,0 +1,16 @@
+    .section .rodata
+hello:
+    .string "Hello world!\n"
+
+    .section .text
+    .globl print
+print:
+    .cfi_startproc
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    mov     $hello, %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+    ret
+    .cfi_endproc
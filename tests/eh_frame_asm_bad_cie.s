@@ -0,0 +1,13 @@
+    .section .text
+    .globl _start
+_start:
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
+
+    # FDE with CIE pointer before the start of .eh_frame
+    .section .eh_frame,"a",@unwind
+    .long   12
+    .long   0x100
+    .quad   0
@@ -0,0 +1,32 @@
+    # the ELF header is mapped at __ehdr_start
+    .section .text
+    .globl _start
+_start:
+    lea     __ehdr_start(%rip), %rax
+    cmpl    $0x464c457f, (%rax)
+    jne     fail
+    lea     __executable_start(%rip), %rcx
+    cmp     %rax, %rcx
+    jne     fail
+
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    lea     hello(%rip), %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
+
+fail:
+    # _exit(1)
+    mov     $1, %rdi
+    mov     $60, %rax
+    syscall
+
+    .section .rodata
+hello:
+    .string "Hello world!\n"
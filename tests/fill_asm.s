@@ -0,0 +1,25 @@
+    # the gap before the aligned section is filled by linker script
+    .section .text
+    .globl _start
+_start:
+    call    hello
+
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
+
+    .section .text.hello, "ax"
+    .p2align 4
+hello:
+    # write(1, message, 13)
+    mov     $1, %rdi
+    lea     message(%rip), %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+    ret
+
+    .section .rodata
+message:
+    .string "Hello world!\n"
@@ -0,0 +1,38 @@
+    .section .rodata
+hello:
+    .string "Hello world!\n"
+
+    .section .rodata.unused,"a"
+unused_string:
+    .string "This is never referenced\n"
+
+    .section .text.unused,"ax"
+unused:
+    lea     unused_string(%rip), %rsi
+    ret
+
+    # kept by --gc-keep-exported only
+    .section .text.exported,"ax"
+    .globl exported
+exported:
+    ret
+
+    # SHF_GNU_RETAIN: kept even if unreferenced
+    .section .text.retain,"axR"
+retain:
+    ret
+
+    .section .text
+    .globl _start
+_start:
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    mov     $hello, %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
@@ -0,0 +1,25 @@
+    .section .rodata
+hello:
+    .string "Hello world!\n"
+length:
+    .quad   13
+
+    .section .text
+    .globl _start
+_start:
+    # R_X86_64_GOTPC32
+    lea     _GLOBAL_OFFSET_TABLE_(%rip), %rbx
+    # R_X86_64_GOT32: entries of hello and length
+    mov     hello@GOT(%rbx), %rsi
+    mov     length@GOT(%rbx), %rdx
+    mov     (%rdx), %rdx
+
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    mov     $1, %rax
+    syscall
+
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
@@ -0,0 +1,23 @@
+    .section .rodata
+hello:
+    .string "Hello world!\n"
+
+    .section .text
+    .globl _start
+_start:
+    # R_X86_64_GOTPC32
+    lea     _GLOBAL_OFFSET_TABLE_(%rip), %rbx
+    # R_X86_64_GOTOFF64
+    movabs  $hello@GOTOFF, %rsi
+    add     %rbx, %rsi
+
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
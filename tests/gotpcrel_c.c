@@ -0,0 +1,14 @@
+// globals are loaded from their GOT entries with -fPIC, R_X86_64_GOTPCREL
+const char *message = "Hello world!\n";
+long length = 13;
+
+void _start() {
+    long ret;
+    // write(1, message, length)
+    asm volatile("syscall"
+                 : "=a"(ret)
+                 : "a"(1), "D"(1), "S"(message), "d"(length)
+                 : "rcx", "r11", "memory");
+    // _exit(0)
+    asm volatile("syscall" : : "a"(60), "D"(0));
+}
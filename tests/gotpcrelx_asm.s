@@ -0,0 +1,24 @@
+    .section .rodata
+hello:
+    .string "Hello world!\n"
+
+    .section .text
+    .globl _start
+_start:
+    # R_X86_64_REX_GOTPCRELX, relaxed to lea hello(%rip), %rsi
+    mov     hello@GOTPCREL(%rip), %rsi
+    # R_X86_64_GOTPCRELX, calls through the GOT entry
+    call    *print@GOTPCREL(%rip)
+
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
+
+print:
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+    ret
@@ -0,0 +1,23 @@
+    # greet is an indirect function: its address is returned by the resolver
+    .section .text
+    .globl greet
+    .type greet, @gnu_indirect_function
+greet:
+    lea     greet_impl(%rip), %rax
+    ret
+
+greet_impl:
+    call    print
+    ret
+
+    .globl _start
+_start:
+    # through .iplt
+    call    greet
+    # through a pointer relocated by R_X86_64_IRELATIVE
+    call    *greet_ptr(%rip)
+    call    exit
+
+    .section .data
+greet_ptr:
+    .quad   greet
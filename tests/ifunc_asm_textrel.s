@@ -0,0 +1,6 @@
+    # absolute address of an indirect function in .text
+    .section .text
+    .globl get_greet
+get_greet:
+    movabs  $greet, %rax
+    ret
@@ -0,0 +1,33 @@
+    # .data is stored at 0x500000, but runs where it is mapped
+    .section .text
+    .globl _start
+_start:
+    movabs  $data_load, %rax
+    cmp     $0x500000, %rax
+    jne     fail
+
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    lea     hello(%rip), %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
+
+fail:
+    # _exit(1)
+    mov     $1, %rdi
+    mov     $60, %rax
+    syscall
+
+    .section .data
+hello:
+    .string "Hello world!\n"
+
+    .section .aux,"aw"
+aux:
+    .quad   0
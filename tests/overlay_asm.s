@@ -0,0 +1,35 @@
+    # two overlays share one address, the one mapped last runs
+    .section .text
+    .globl _start
+_start:
+    call    overlay1
+
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
+
+    .section .overlay0, "ax"
+overlay0:
+    # _exit(1)
+    mov     $1, %rdi
+    mov     $60, %rax
+    syscall
+
+    .section .overlay1, "ax"
+overlay1:
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    lea     hello(%rip), %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+    ret
+
+hello:
+    .string "Hello world!\n"
+
+    .section .rodata
+    # where overlays are copied from
+    .quad   __load_start_overlay1
+    .quad   __load_stop_overlay1
@@ -0,0 +1,27 @@
+    # calls between local functions in adjacent sections, R_X86_64_PC32
+    .section .text.start,"ax"
+    .globl _start
+_start:
+    lea     far(%rip), %rax
+    call    print
+    call    exit
+
+    .section .text.print,"ax"
+print:
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    lea     hello(%rip), %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+    ret
+
+exit:
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
+
+    .section .rodata
+hello:
+    .string "Hello world!\n"
@@ -0,0 +1,29 @@
+    # offset to a symbol 8 GiB away, R_X86_64_PC64
+    .text
+    .globl _start
+_start:
+    # far = offset + its content
+    lea     offset(%rip), %rax
+    add     offset(%rip), %rax
+    movabs  $0x200401000, %rcx
+    cmp     %rcx, %rax
+    jne     exit
+
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    lea     hello(%rip), %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+
+exit:
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
+
+    .section .rodata
+offset:
+    .quad   far - .
+hello:
+    .string "Hello world!\n"
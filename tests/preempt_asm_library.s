@@ -0,0 +1,39 @@
+# message is global, so the executable may preempt it
+    .section .rodata
+    .globl message
+    .type message, @object
+    .size message, 14
+message:
+    .string "Hello world!\n"
+
+    # absolute address of a preemptible symbol, bound by ld.so
+    .section .data
+message_ptr:
+    .quad   message
+
+    .section .text
+    .globl print
+    .type print, @function
+print:
+    # write(1, message, 13)
+    mov     $1, %rdi
+    mov     message_ptr(%rip), %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+
+    # GOT entry of a preemptible symbol, filled by ld.so
+    lea     _GLOBAL_OFFSET_TABLE_(%rip), %rbx
+    mov     $1, %rdi
+    movq    message@GOT(%rbx), %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+
+    # not relaxed to lea, the address is only known at runtime
+    mov     $1, %rdi
+    movq    message@GOTPCREL(%rip), %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+    ret
@@ -0,0 +1,9 @@
+void print();
+
+// preempts message of the shared library
+const char message[] = "Other world!\n";
+
+int main() {
+  print();
+  return 0;
+}
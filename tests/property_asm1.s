@@ -0,0 +1,16 @@
+    # GNU_PROPERTY_X86_FEATURE_1_AND: IBT | SHSTK
+    .section .note.gnu.property,"a"
+    .align 8
+    .long 4, 16, 5
+    .string "GNU"
+    .long 0xc0000002, 4, 3, 0
+
+    .section .text
+    .globl _start
+_start:
+    call    print
+
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
@@ -0,0 +1,21 @@
+    # GNU_PROPERTY_X86_FEATURE_1_AND: IBT only
+    .section .note.gnu.property,"a"
+    .align 8
+    .long 4, 16, 5
+    .string "GNU"
+    .long 0xc0000002, 4, 1, 0
+
+    .section .rodata
+hello:
+    .string "Hello world!\n"
+
+    .section .text
+    .globl print
+print:
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    mov     $hello, %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+    ret
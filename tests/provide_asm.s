@@ -0,0 +1,18 @@
+    # message and its length are provided by linker script
+    .section .text
+    .globl _start
+_start:
+    # write(1, message, message_length)
+    mov     $1, %rdi
+    lea     message(%rip), %rsi
+    mov     $message_length, %rdx
+    mov     $1, %rax
+    syscall
+
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
+
+hello:
+    .string "Hello world!\n"
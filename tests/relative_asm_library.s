@@ -0,0 +1,19 @@
+    .section .rodata
+hello:
+    .string "Hello world!\n"
+
+    # absolute address, relocated by R_X86_64_RELATIVE at load time
+    .section .data
+hello_ptr:
+    .quad   hello
+
+    .section .text
+    .globl print
+print:
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    mov     hello_ptr(%rip), %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+    ret
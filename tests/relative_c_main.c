@@ -0,0 +1,6 @@
+void print();
+
+int main() {
+  print();
+  return 0;
+}
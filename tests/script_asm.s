@@ -0,0 +1,23 @@
+    # sections are merged by linker script
+    .section .text.start,"ax"
+    .globl start
+start:
+    jmp     print
+
+    .section .text.print,"ax"
+print:
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    lea     hello(%rip), %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
+
+    .section .rodata.hello,"a"
+hello:
+    .string "Hello world!\n"
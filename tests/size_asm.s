@@ -0,0 +1,22 @@
+    .section .rodata
+    # global symbol: the assembler cannot resolve its size
+    .globl hello
+hello:
+    .string "Hello world!\n"
+    .size hello, . - hello
+
+    .section .text
+    .globl _start
+_start:
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    mov     $hello, %rsi
+    # R_X86_64_SIZE32: size of hello without the NULL terminator
+    mov     $hello@SIZE - 1, %edx
+    mov     $1, %rax
+    syscall
+
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
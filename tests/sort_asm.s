@@ -0,0 +1,32 @@
+    # input sections are reordered by linker script
+    .section .text.b,"ax"
+    .globl _start
+_start:
+    jmp     print
+
+    .section .text.a,"ax"
+print:
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    lea     hello(%rip), %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
+
+    .section .data.byte,"aw"
+byte:
+    .byte   1
+
+    .section .data.quad,"aw"
+    .balign 8
+quad:
+    .quad   2
+
+    .section .data.hello,"aw"
+hello:
+    .string "Hello world!\n"
@@ -0,0 +1,73 @@
+    .section .rodata
+hello:
+    .string "Hello world!\n"
+
+    # absolute address, relocated by _start itself
+    .section .data
+hello_ptr:
+    .quad   hello
+
+    .section .text
+    .globl _start
+_start:
+    # skip argc, argv and envp on the stack to reach auxv
+    mov     (%rsp), %rax
+    lea     16(%rsp, %rax, 8), %rcx
+1:
+    add     $8, %rcx
+    cmpq    $0, -8(%rcx)
+    jne     1b
+
+    # load base: AT_PHDR minus offset of program headers
+1:
+    mov     (%rcx), %rax
+    add     $16, %rcx
+    cmp     $3, %rax
+    jne     1b
+    mov     -8(%rcx), %rbx
+    sub     $64, %rbx
+
+    # find DT_RELA and DT_RELASZ in _DYNAMIC
+    lea     _DYNAMIC(%rip), %rcx
+1:
+    mov     (%rcx), %rax
+    test    %rax, %rax
+    jz      3f
+    cmp     $7, %rax
+    jne     2f
+    mov     8(%rcx), %rsi
+2:
+    cmp     $8, %rax
+    jne     4f
+    mov     8(%rcx), %rdx
+4:
+    add     $16, %rcx
+    jmp     1b
+3:
+    # apply R_X86_64_RELATIVE: *(B + r_offset) = B + r_addend
+    add     %rbx, %rsi
+    add     %rsi, %rdx
+5:
+    cmp     %rdx, %rsi
+    jae     6f
+    cmpl    $8, 8(%rsi)
+    jne     7f
+    mov     (%rsi), %rdi
+    mov     16(%rsi), %rax
+    add     %rbx, %rax
+    mov     %rax, (%rbx, %rdi)
+7:
+    add     $24, %rsi
+    jmp     5b
+6:
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    mov     hello_ptr(%rip), %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
@@ -0,0 +1,10 @@
+    # absolute address in .text needs a dynamic relocation in .text
+    .section .text
+    .globl get
+get:
+    movabs  $value, %rax
+    ret
+
+    .section .data
+value:
+    .quad   0
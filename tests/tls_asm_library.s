@@ -0,0 +1,21 @@
+    .section .tdata,"awT",@progbits
+hello:
+    .string "Hello world!\n"
+
+    .section .text
+    .globl print
+print:
+    # general dynamic model: get address of hello via __tls_get_addr
+    .byte   0x66
+    leaq    hello@tlsgd(%rip), %rdi
+    .value  0x6666
+    rex64
+    call    __tls_get_addr@PLT
+
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    mov     %rax, %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+    ret
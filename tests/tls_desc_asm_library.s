@@ -0,0 +1,20 @@
+    .section .tdata,"awT",@progbits
+hello:
+    .string "Hello world!\n"
+
+    .section .text
+    .globl print
+print:
+    # TLS descriptor model: get offset of hello from thread pointer via
+    # the descriptor in GOT
+    lea     hello@tlsdesc(%rip), %rax
+    call    *hello@tlscall(%rax)
+
+    # write(1, hello, 13)
+    mov     %fs:0, %rsi
+    add     %rax, %rsi
+    mov     $1, %rdi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+    ret
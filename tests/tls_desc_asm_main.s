@@ -0,0 +1,21 @@
+    .section .tdata,"awT",@progbits
+hello:
+    .string "Hello world!\n"
+
+    .section .text
+    .globl _start
+_start:
+    # TLS descriptor model in executable: relaxed to a constant offset from
+    # thread pointer, which ld.so sets up
+    lea     hello@tlsdesc(%rip), %rax
+    call    *hello@tlscall(%rax)
+
+    # write(1, hello, 13)
+    mov     %fs:0, %rsi
+    add     %rax, %rsi
+    mov     $1, %rdi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+
+    call    exit
@@ -0,0 +1,21 @@
+    .section .text
+    .globl _start
+_start:
+    # general dynamic model: hello is defined in the shared library
+    .byte   0x66
+    leaq    hello@tlsgd(%rip), %rdi
+    .value  0x6666
+    rex64
+    call    __tls_get_addr@PLT
+
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    mov     %rax, %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
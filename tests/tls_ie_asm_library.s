@@ -0,0 +1,17 @@
+    .section .tdata,"awT",@progbits
+hello:
+    .string "Hello world!\n"
+
+    .section .text
+    .globl print
+print:
+    # initial exec model: offset of hello from thread pointer is in GOT
+    movq    hello@gottpoff(%rip), %rsi
+    add     %fs:0, %rsi
+
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+    ret
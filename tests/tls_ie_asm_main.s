@@ -0,0 +1,19 @@
+    .section .tdata,"awT",@progbits
+hello:
+    .string "Hello world!\n"
+
+    .section .text
+    .globl _start
+_start:
+    # initial exec model in executable: offset from thread pointer is
+    # filled in GOT at link time
+    movq    hello@gottpoff(%rip), %rsi
+    add     %fs:0, %rsi
+
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+
+    call    exit
@@ -0,0 +1,18 @@
+    .section .text
+    .globl _start
+_start:
+    # initial exec model: hello is defined in the shared library, so the
+    # offset from thread pointer is filled in GOT by dynamic linker
+    movq    hello@gottpoff(%rip), %rsi
+    add     %fs:0, %rsi
+
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
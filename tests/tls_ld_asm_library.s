@@ -0,0 +1,20 @@
+    .section .tdata,"awT",@progbits
+    .zero   16
+hello:
+    .string "Hello world!\n"
+
+    .section .text
+    .globl print
+print:
+    # local dynamic model: get base of the TLS block via __tls_get_addr,
+    # then add the offset of hello in it
+    leaq    hello@tlsld(%rip), %rdi
+    call    __tls_get_addr@PLT
+    leaq    hello@dtpoff(%rax), %rsi
+
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+    ret
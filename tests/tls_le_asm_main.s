@@ -0,0 +1,33 @@
+    .section .tdata,"awT",@progbits
+hello:
+    .string "Hello world!\n"
+
+    .section .tbss,"awT",@nobits
+    .align  4
+x:
+    .zero   4
+
+    .section .text
+    .globl _start
+_start:
+    # local exec model: offset from thread pointer is known at link time,
+    # after .tdata and .tbss are laid out
+    movl    $1, %fs:x@tpoff
+    cmpl    $1, %fs:x@tpoff
+    jne     fail
+    mov     %fs:0, %rsi
+    lea     hello@tpoff(%rsi), %rsi
+
+    # write(1, hello, 13)
+    mov     $1, %rdi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+
+    call    exit
+
+fail:
+    # _exit(1)
+    mov     $1, %rdi
+    mov     $60, %rax
+    syscall
@@ -0,0 +1,19 @@
+    # missing is defined nowhere, unresolved symbols resolve to 0
+    .section .text
+    .globl _start
+_start:
+    # write(missing + 1, hello, 13)
+    mov     $missing, %rdi
+    add     $1, %rdi
+    lea     hello(%rip), %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+
+    # _exit(0)
+    xor     %rdi, %rdi
+    mov     $60, %rax
+    syscall
+
+hello:
+    .string "Hello world!\n"
@@ -0,0 +1,25 @@
+    # message and done are defined by the executable
+    .section .text
+    .globl print
+print:
+    # write(1, message, 13), address loaded from GOT
+    mov     $1, %rdi
+    movq    message@GOTPCREL(%rip), %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+
+    # write(1, message, 13), absolute address relocated by ld.so
+    mov     $1, %rdi
+    mov     message_ptr(%rip), %rsi
+    mov     $13, %rdx
+    mov     $1, %rax
+    syscall
+
+    # only calls go through .plt
+    call    done@PLT
+    ret
+
+    .section .data
+message_ptr:
+    .quad   message
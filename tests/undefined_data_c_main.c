@@ -0,0 +1,13 @@
+#include <unistd.h>
+
+void print();
+
+// referenced by the shared library
+const char message[] = "Hello world!\n";
+
+void done() { write(1, "Done!\n", 6); }
+
+int main() {
+  print();
+  return 0;
+}